@@ -0,0 +1,111 @@
+//! Now-Playing Announcement Module
+//!
+//! Posts an embed to a guild's configured text channel whenever a track starts.
+//! Only one announcement per guild is kept: the previous message is deleted
+//! before the next one is posted, so the channel doesn't fill up with old tracks.
+
+use crate::logging::{LogEntry, LogLevel};
+use crate::state::{SharedState, TrackMetadata};
+use serenity::all::{ChannelId, CreateEmbed, CreateEmbedFooter, CreateMessage, Http, MessageId};
+use songbird::{Event, EventContext, EventHandler};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The last announcement posted in each guild, keyed by guild ID.
+pub type AnnouncementLog = Arc<Mutex<HashMap<u64, (ChannelId, MessageId)>>>;
+
+/// Listens for a track's first `TrackEvent::Play` and announces it.
+pub struct NowPlayingAnnouncer {
+    pub uuid: String,
+    pub guild_id: u64,
+    pub state: SharedState,
+    pub http: Arc<Http>,
+    pub metadata: TrackMetadata,
+    pub announcements: AnnouncementLog,
+    /// `Play` also fires when resuming from pause; only the first one is announced.
+    pub announced: AtomicBool,
+}
+
+impl NowPlayingAnnouncer {
+    /// Builds the now-playing embed for the track.
+    fn build_embed(&self) -> CreateEmbed {
+        let meta = &self.metadata;
+        let mut embed = CreateEmbed::new()
+            .title(format!("Now Playing: {}", meta.title))
+            .url(&meta.url)
+            .footer(CreateEmbedFooter::new(format!(
+                "Requested by {}",
+                meta.added_by
+            )));
+
+        if let Some(artist) = &meta.artist {
+            embed = embed.field("Artist", artist, true);
+        }
+        if let Some(secs) = meta.duration_secs {
+            embed = embed.field(
+                "Duration",
+                format!("{:02}:{:02}", secs / 60, secs % 60),
+                true,
+            );
+        }
+        if let Some(thumb) = &meta.thumbnail_url {
+            embed = embed.thumbnail(thumb);
+        }
+        embed
+    }
+
+    /// Logs an error for this guild to the shared system logs.
+    fn log_error(&self, msg: &str) {
+        let entry = LogEntry::new(
+            LogLevel::Error,
+            Some(self.uuid.clone()),
+            Some(self.guild_id),
+            msg,
+        );
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.log_entry(entry);
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for NowPlayingAnnouncer {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if self.announced.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+
+        let channel_id = {
+            let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state
+                .accounts
+                .get(&self.uuid)
+                .and_then(|acc| acc.settings_for(self.guild_id).announce_channel_id)
+        }?;
+
+        let previous = self
+            .announcements
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.guild_id);
+        if let Some((prev_channel, prev_message)) = previous {
+            let _ = prev_channel.delete_message(&self.http, prev_message).await;
+        }
+
+        let message = CreateMessage::new().embed(self.build_embed());
+        match ChannelId::new(channel_id)
+            .send_message(&self.http, message)
+            .await
+        {
+            Ok(msg) => {
+                self.announcements
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(self.guild_id, (msg.channel_id, msg.id));
+            }
+            Err(e) => self.log_error(&format!("Failed to post now-playing announcement: {}", e)),
+        }
+
+        None
+    }
+}
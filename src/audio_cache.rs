@@ -0,0 +1,214 @@
+//! Offline Audio Cache Module
+//!
+//! Keeps Opus/OGG copies of played tracks in `cache/audio/` so repeat plays
+//! come from disk instead of the network. The index records each file's size,
+//! last use and display metadata, so a cached track plays even when the
+//! upstream site is down. The least recently used files are evicted once the
+//! cache exceeds its size cap.
+
+use crate::config::ConfigManager;
+use crate::logging::{self, LogLevel};
+use crate::metadata_cache::{CachedMetadata, normalize_url};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+static SHARED: OnceLock<Arc<AudioCache>> = OnceLock::new();
+
+/// A cached audio file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AudioEntry {
+    file_name: String,
+    size_bytes: u64,
+    /// Unix timestamp in milliseconds of the last play, for LRU eviction.
+    last_used_ms: i64,
+    metadata: CachedMetadata,
+}
+
+struct Index {
+    entries: HashMap<String, AudioEntry>,
+    /// Normalized URLs currently being transcoded.
+    pending: HashSet<String>,
+}
+
+/// On-disk cache of transcoded tracks, keyed by normalized URL.
+pub struct AudioCache {
+    dir: PathBuf,
+    index: Mutex<Index>,
+}
+
+impl AudioCache {
+    /// The process-wide cache, loaded from disk on first use.
+    pub fn shared() -> Arc<AudioCache> {
+        SHARED
+            .get_or_init(|| {
+                let dir = ConfigManager::data_dir().join("cache").join("audio");
+                Arc::new(Self::load(dir))
+            })
+            .clone()
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("index.json")
+    }
+
+    /// Loads the index, dropping entries whose file has gone missing.
+    fn load(dir: PathBuf) -> Self {
+        let mut entries: HashMap<String, AudioEntry> = fs::read_to_string(dir.join("index.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        entries.retain(|_, entry| dir.join(&entry.file_name).is_file());
+
+        Self {
+            dir,
+            index: Mutex::new(Index {
+                entries,
+                pending: HashSet::new(),
+            }),
+        }
+    }
+
+    /// Returns the cached file and metadata for a URL, marking it as recently used.
+    ///
+    /// The new last use stays in memory until the next insert or clear writes the
+    /// index, so lookups never touch the disk; eviction order may lag after a restart.
+    pub fn lookup(&self, url: &str) -> Option<(PathBuf, CachedMetadata)> {
+        let mut index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = index.entries.get_mut(&normalize_url(url))?;
+        entry.last_used_ms = Local::now().timestamp_millis();
+        Some((self.dir.join(&entry.file_name), entry.metadata.clone()))
+    }
+
+    /// Whether a URL is cached, without marking it as used.
+    pub fn contains(&self, url: &str) -> bool {
+        let index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        index.entries.contains_key(&normalize_url(url))
+    }
+
+    /// Reserves a URL for caching, returning the temporary path to transcode into.
+    ///
+    /// Returns `None` if the URL is already cached or being cached.
+    pub fn begin(&self, url: &str) -> Option<PathBuf> {
+        let key = normalize_url(url);
+        let mut index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        if index.entries.contains_key(&key) || !index.pending.insert(key.clone()) {
+            return None;
+        }
+        if let Err(e) = fs::create_dir_all(&self.dir) {
+            logging::record(
+                LogLevel::Warn,
+                format!("Failed to create audio cache directory: {}", e),
+            );
+            index.pending.remove(&key);
+            return None;
+        }
+        Some(self.dir.join(format!("{}.part", file_stem(&key))))
+    }
+
+    /// Moves a finished transcode into the cache, then evicts down to `max_bytes`.
+    pub fn finish(
+        &self,
+        url: &str,
+        temp: &Path,
+        metadata: CachedMetadata,
+        max_bytes: u64,
+    ) -> io::Result<()> {
+        let key = normalize_url(url);
+        let mut index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        index.pending.remove(&key);
+
+        let file_name = format!("{}.ogg", file_stem(&key));
+        fs::rename(temp, self.dir.join(&file_name))?;
+        let size_bytes = fs::metadata(self.dir.join(&file_name))?.len();
+
+        index.entries.insert(
+            key,
+            AudioEntry {
+                file_name,
+                size_bytes,
+                last_used_ms: Local::now().timestamp_millis(),
+                metadata,
+            },
+        );
+        self.evict(&mut index, max_bytes);
+        self.save(&index)
+    }
+
+    /// Releases a reservation after a failed transcode.
+    pub fn abort(&self, url: &str, temp: &Path) {
+        let mut index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        index.pending.remove(&normalize_url(url));
+        let _ = fs::remove_file(temp);
+    }
+
+    /// Number of cached tracks and their total size in bytes.
+    pub fn usage(&self) -> (usize, u64) {
+        let index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let bytes = index.entries.values().map(|e| e.size_bytes).sum();
+        (index.entries.len(), bytes)
+    }
+
+    /// Deletes every cached track. Transcodes in progress still complete.
+    pub fn clear(&self) {
+        let mut index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        for entry in index.entries.values() {
+            let _ = fs::remove_file(self.dir.join(&entry.file_name));
+        }
+        index.entries.clear();
+        self.save_logged(&index);
+    }
+
+    /// Removes least recently used files until the total size fits in `max_bytes`.
+    fn evict(&self, index: &mut Index, max_bytes: u64) {
+        let mut total: u64 = index.entries.values().map(|e| e.size_bytes).sum();
+        if total <= max_bytes {
+            return;
+        }
+
+        let mut by_use: Vec<(String, i64)> = index
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.last_used_ms))
+            .collect();
+        by_use.sort_by_key(|(_, used)| *used);
+
+        for (key, _) in by_use {
+            if total <= max_bytes {
+                break;
+            }
+            if let Some(entry) = index.entries.remove(&key) {
+                let _ = fs::remove_file(self.dir.join(&entry.file_name));
+                total -= entry.size_bytes;
+            }
+        }
+    }
+
+    fn save(&self, index: &Index) -> io::Result<()> {
+        let content = serde_json::to_string(&index.entries).map_err(io::Error::other)?;
+        fs::write(self.index_path(), content)
+    }
+
+    fn save_logged(&self, index: &Index) {
+        if let Err(e) = self.save(index) {
+            logging::record(
+                LogLevel::Warn,
+                format!("Failed to write audio cache index: {}", e),
+            );
+        }
+    }
+}
+
+/// A stable file name for a cache key (64-bit FNV-1a, hex encoded).
+///
+/// `DefaultHasher` isn't guaranteed stable across Rust releases, which would orphan cached files.
+fn file_stem(key: &str) -> String {
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
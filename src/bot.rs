@@ -4,15 +4,38 @@
 //! The BotManager acts as a supervisor, listening for lifecycle commands.
 //! The BotInstance is an isolated worker managing a specific Discord connection.
 
-use crate::sources::SourceResolver;
-use crate::state::{AccountState, BotCommand, BotStatus, GuildState, SharedState, TrackMetadata};
+use crate::announce::{AnnouncementLog, NowPlayingAnnouncer};
+use crate::config::ConfigManager;
+use crate::diagnostics::{self, DependencyReport};
+use crate::gateway::{GatewayEvent, GatewayHandler};
+use crate::logging::{LogEntry, LogLevel};
+use crate::metadata_cache::{CachedMetadata, normalize_url};
+use crate::player::{PlayerButton, PlayerView};
+use crate::playlist::{self, ExportFormat, PlaylistEntry, TrackList};
+use crate::radio::{IcyWatcher, StreamTitles};
+use crate::scheduler::{JobAction, MAX_CATCH_UP_MINUTES, ONCE_FORMAT, Schedule, ScheduledJob};
+use crate::sources::{SourceError, SourceResolver, StderrTail};
+use crate::speech::{NextTrackSpeaker, SpeechQueue};
+use crate::state::{
+    AccountState, AppState, AudioCacheSettings, BotCommand, BotStatus, DisconnectAction,
+    GuildSettings, GuildState, MAX_HISTORY, NameId, SharedState, SleepAfter, SleepTimer,
+    ToolSettings, TrackMetadata, VoiceChannel, VoiceChannelKind,
+};
+use chrono::{DateTime, Local};
 use serenity::Client;
-use serenity::all::{ChannelId, ChannelType, GatewayIntents, GuildId, Http};
-use songbird::tracks::PlayMode;
+use serenity::all::{
+    Cache, ChannelId, ChannelType, ComponentInteraction, CreateInteractionResponseFollowup,
+    CreateMessage, EditMessage, EditVoiceState, Error as SerenityError, GatewayIntents, Guild,
+    GuildChannel, GuildId, GuildPagination, Http, MessageId, Permissions, UserId,
+};
+use serenity::builder::Builder;
+use songbird::tracks::{LoopState, PlayMode, TrackHandle};
 use songbird::{Event, EventContext, EventHandler, SerenityInit, TrackEvent};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, MutexGuard};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::{self, Receiver};
 
 /// Lifecycle commands sent to the Global Manager.
@@ -22,26 +45,236 @@ pub enum ManagerCommand {
     StartBot { uuid: String },
     /// Shuts down the bot instance for the given UUID.
     StopBot { uuid: String },
+    /// Re-runs the yt-dlp/ffmpeg dependency health check.
+    CheckDependencies,
+    /// Runs yt-dlp's self-update, unless tracks are currently resolving.
+    UpdateYtDlp,
 }
 
+/// How often the supervisor checks whether a scheduled yt-dlp update is due.
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(600);
+/// How often the supervisor checks for due scheduled jobs.
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(10);
+/// Delay before reposting a player message after the first failure; it doubles with each
+/// further failure.
+const PLAYER_RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Consecutive failures to post a player message before its channel setting is turned off.
+const MAX_PLAYER_POST_FAILURES: u32 = 5;
+/// How many of the most recently played tracks autoplay avoids repeating.
+const AUTOPLAY_RECENT: usize = 50;
+/// Autoplay gives up after this many of its picks in a row fail to play.
+const MAX_AUTOPLAY_FAILURES: u32 = 3;
+/// How long a loaded autoplay fallback playlist is reused before it is loaded again.
+const FALLBACK_PLAYLIST_TTL: Duration = Duration::from_secs(1800);
+
 /// The Supervisor that manages the lifecycle of all bot threads.
 pub struct BotManager {
     state: SharedState,
     cmd_rx: Receiver<ManagerCommand>,
+    /// When yt-dlp last updated (or the app started), for the periodic update.
+    last_yt_dlp_update: Instant,
+    /// When the schedule was last checked; jobs due since then run on the next check.
+    /// Starts `MAX_CATCH_UP_MINUTES` back, so runs missed while the app was closed catch up.
+    last_schedule_check: DateTime<Local>,
 }
 
 impl BotManager {
     /// Creates a new Manager instance.
     pub fn new(state: SharedState, cmd_rx: Receiver<ManagerCommand>) -> Self {
-        Self { state, cmd_rx }
+        Self {
+            state,
+            cmd_rx,
+            last_yt_dlp_update: Instant::now(),
+            last_schedule_check: Local::now() - chrono::Duration::minutes(MAX_CATCH_UP_MINUTES),
+        }
     }
 
     /// Starts the supervisor loop.
     pub async fn run(mut self) {
-        while let Some(cmd) = self.cmd_rx.recv().await {
-            match cmd {
-                ManagerCommand::StartBot { uuid } => self.spawn_bot(uuid).await,
-                ManagerCommand::StopBot { uuid } => self.kill_bot(uuid).await,
+        let mut maintenance = tokio::time::interval(MAINTENANCE_INTERVAL);
+        let mut schedule = tokio::time::interval(SCHEDULE_INTERVAL);
+
+        loop {
+            tokio::select! {
+                cmd = self.cmd_rx.recv() => {
+                    let Some(cmd) = cmd else { break };
+                    match cmd {
+                        ManagerCommand::StartBot { uuid } => self.spawn_bot(uuid).await,
+                        ManagerCommand::StopBot { uuid } => self.kill_bot(uuid).await,
+                        ManagerCommand::CheckDependencies => self.check_dependencies(),
+                        ManagerCommand::UpdateYtDlp => self.update_yt_dlp(),
+                    }
+                }
+                _ = maintenance.tick() => self.run_scheduled_update(),
+                _ = schedule.tick() => self.run_due_jobs().await,
+            }
+        }
+    }
+
+    /// Starts a yt-dlp update if the configured auto-update interval has elapsed and
+    /// no track is resolving.
+    fn run_scheduled_update(&mut self) {
+        let interval = {
+            let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            // Retried on a later check instead of warning every time
+            if state.resolving_tracks > 0 {
+                return;
+            }
+            state.tool_settings.auto_update_hours
+        };
+        let Some(hours) = interval else { return };
+
+        if self.last_yt_dlp_update.elapsed() >= Duration::from_secs(hours * 3600) {
+            self.update_yt_dlp();
+        }
+    }
+
+    /// Runs every enabled job scheduled since the last check. One-shot jobs are disabled
+    /// afterwards, or when their time passed before the catch-up window.
+    async fn run_due_jobs(&mut self) {
+        let now = Local::now();
+        let after = std::mem::replace(&mut self.last_schedule_check, now);
+
+        let due: Vec<ScheduledJob> = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let mut due = Vec::new();
+            let mut missed = Vec::new();
+            for job in &mut state.scheduled_jobs {
+                if !job.enabled {
+                    continue;
+                }
+                // A run recorded before a restart isn't repeated by the catch-up
+                let since = job.last_run_time().map_or(after, |last| last.max(after));
+                if job.schedule.fires_between(since, now) {
+                    job.last_run = Some(now.format(ONCE_FORMAT).to_string());
+                    if matches!(job.schedule, Schedule::Once(_)) {
+                        job.enabled = false;
+                    }
+                    due.push(job.clone());
+                } else if job.schedule.has_passed(after) {
+                    job.enabled = false;
+                    missed.push(job.clone());
+                }
+            }
+            if !due.is_empty() || !missed.is_empty() {
+                let _ = ConfigManager::save(&ConfigManager::update_from_state(&state));
+            }
+            for job in missed {
+                state.log_entry(LogEntry::new(
+                    LogLevel::Warn,
+                    Some(job.account_uuid),
+                    None,
+                    format!(
+                        "Scheduled job \"{}\" was missed: it was due more than {} minutes ago. It has been disabled.",
+                        job.name, MAX_CATCH_UP_MINUTES
+                    ),
+                ));
+            }
+            due
+        };
+
+        for job in due {
+            self.run_job(job).await;
+        }
+    }
+
+    /// Carries out a scheduled job's action.
+    async fn run_job(&self, job: ScheduledJob) {
+        let (alias, command_tx) = {
+            let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            match state.accounts.get(&job.account_uuid) {
+                Some(account) => (account.alias.clone(), account.command_tx.clone()),
+                None => (String::new(), None),
+            }
+        };
+        let log = |level: LogLevel, guild_id: Option<u64>, msg: String| {
+            let entry = LogEntry::new(level, Some(job.account_uuid.clone()), guild_id, msg);
+            self.state
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .log_entry(entry);
+        };
+        if alias.is_empty() {
+            log(
+                LogLevel::Warn,
+                None,
+                format!(
+                    "Scheduled job \"{}\" skipped: its account was removed.",
+                    job.name
+                ),
+            );
+            return;
+        }
+        log(
+            LogLevel::Info,
+            None,
+            format!(
+                "Running scheduled job \"{}\" ({})",
+                job.name,
+                job.action.label()
+            ),
+        );
+
+        let commands = match &job.action {
+            JobAction::StartBot => {
+                self.spawn_bot(job.account_uuid.clone()).await;
+                return;
+            }
+            JobAction::StopBot => {
+                self.kill_bot(job.account_uuid.clone()).await;
+                return;
+            }
+            JobAction::JoinAndPlay {
+                guild_id,
+                channel_id,
+                source,
+            } => {
+                let guild_id = *guild_id;
+                let mut commands = vec![BotCommand::Join {
+                    guild_id,
+                    channel_id: *channel_id,
+                }];
+                let source = source.trim().to_string();
+                let requested_by = format!("Schedule: {}", job.name);
+                if playlist::is_playlist(&source) {
+                    commands.push(BotCommand::ImportPlaylist {
+                        guild_id,
+                        source,
+                        requested_by,
+                    });
+                } else if !source.is_empty() {
+                    commands.push(BotCommand::Play {
+                        guild_id,
+                        url: source,
+                        requested_by,
+                    });
+                }
+                commands
+            }
+            JobAction::StopAndLeave { guild_id } => vec![
+                BotCommand::Stop {
+                    guild_id: *guild_id,
+                },
+                BotCommand::Leave {
+                    guild_id: *guild_id,
+                },
+            ],
+        };
+
+        let Some(tx) = command_tx else {
+            log(
+                LogLevel::Warn,
+                None,
+                format!(
+                    "Scheduled job \"{}\" skipped: {} is not running.",
+                    job.name, alias
+                ),
+            );
+            return;
+        };
+        for cmd in commands {
+            if tx.send(cmd).await.is_err() {
+                break;
             }
         }
     }
@@ -83,6 +316,137 @@ impl BotManager {
         });
     }
 
+    /// Runs the dependency health check off the async runtime and publishes the report.
+    fn check_dependencies(&self) {
+        {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.dependency_check_running {
+                return;
+            }
+            state.dependency_check_running = true;
+        }
+
+        let state_ref = self.state.clone();
+        tokio::spawn(async move {
+            let tools = state_ref
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .tool_settings
+                .clone();
+            let report =
+                tokio::task::spawn_blocking(move || diagnostics::check_dependencies(&tools)).await;
+
+            let mut state = state_ref.lock().unwrap_or_else(|e| e.into_inner());
+            state.dependency_check_running = false;
+            match report {
+                Ok(report) => Self::publish_report(&mut state, report),
+                Err(e) => state.log_entry(LogEntry::new(
+                    LogLevel::Error,
+                    None,
+                    None,
+                    format!("Dependency check failed: {}", e),
+                )),
+            }
+        });
+    }
+
+    /// Logs a dependency report and makes it available to the GUI.
+    fn publish_report(state: &mut AppState, report: DependencyReport) {
+        let level = if report.is_ok() {
+            LogLevel::Info
+        } else {
+            LogLevel::Warn
+        };
+        for line in report.summary_lines() {
+            state.log_entry(LogEntry::new(level, None, None, line));
+        }
+        state.dependency_report = Some(report);
+    }
+
+    /// Runs yt-dlp's self-update off the async runtime, then re-checks dependencies.
+    ///
+    /// Refused while any bot is resolving a track, since yt-dlp is replaced in place.
+    fn update_yt_dlp(&mut self) {
+        {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.yt_dlp_updating {
+                return;
+            }
+            if state.resolving_tracks > 0 {
+                let msg = format!(
+                    "Not updating yt-dlp: {} track(s) are resolving. Try again shortly.",
+                    state.resolving_tracks
+                );
+                state.log_entry(LogEntry::new(LogLevel::Warn, None, None, msg));
+                return;
+            }
+            state.yt_dlp_updating = true;
+            state.log_entry(LogEntry::new(
+                LogLevel::Info,
+                None,
+                None,
+                "Updating yt-dlp...",
+            ));
+        }
+        self.last_yt_dlp_update = Instant::now();
+
+        let state_ref = self.state.clone();
+        tokio::spawn(async move {
+            let tools = state_ref
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .tool_settings
+                .clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let outcome = diagnostics::update_yt_dlp(&tools);
+                (outcome, diagnostics::check_dependencies(&tools))
+            })
+            .await;
+
+            let mut state = state_ref.lock().unwrap_or_else(|e| e.into_inner());
+            state.yt_dlp_updating = false;
+            let (outcome, report) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    state.log_entry(LogEntry::new(
+                        LogLevel::Error,
+                        None,
+                        None,
+                        format!("yt-dlp update failed: {}", e),
+                    ));
+                    return;
+                }
+            };
+
+            let version = |v: &Option<String>| v.clone().unwrap_or_else(|| "unknown".to_string());
+            let entry = match outcome {
+                Ok(outcome) if outcome.changed() => LogEntry::new(
+                    LogLevel::Info,
+                    None,
+                    None,
+                    format!(
+                        "yt-dlp updated from {} to {} ({})",
+                        version(&outcome.old_version),
+                        version(&outcome.new_version),
+                        outcome.path.display()
+                    ),
+                ),
+                Ok(outcome) => LogEntry::new(
+                    LogLevel::Info,
+                    None,
+                    None,
+                    format!(
+                        "yt-dlp is already up to date ({})",
+                        version(&outcome.new_version)
+                    ),
+                ),
+                Err(e) => LogEntry::new(LogLevel::Error, None, None, format!("{:#}", e)),
+            };
+            state.log_entry(entry);
+            Self::publish_report(&mut state, report);
+        });
+    }
+
     /// Signals a bot instance to shut down by dropping its command channel.
     async fn kill_bot(&self, uuid: String) {
         let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
@@ -98,6 +462,8 @@ struct TrackObserver {
     uuid: String,
     guild_id: u64,
     state: SharedState,
+    /// Captured stderr of the track's streaming process.
+    diagnostics: StderrTail,
 }
 
 #[async_trait::async_trait]
@@ -106,17 +472,43 @@ impl EventHandler for TrackObserver {
         if let EventContext::Track(states) = ctx {
             let mut app_state = self.state.lock().unwrap_or_else(|e| e.into_inner());
             let mut playback_errors = Vec::new();
+            let mut autoplay_tx = None;
 
             if let Some(account) = app_state.accounts.get_mut(&self.uuid) {
+                let autoplay = account.settings_for(self.guild_id).autoplay;
+                let command_tx = account.command_tx.clone();
                 if let Some(guild) = account.guilds.get_mut(&self.guild_id) {
                     for (state, _) in *states {
                         match &state.playing {
                             PlayMode::Errored(e) => {
-                                playback_errors.push(format!("{:?}", e));
+                                let error = match self.diagnostics.classify() {
+                                    Some(reason) => format!("{:?} ({})", e, reason),
+                                    None => format!("{:?}", e),
+                                };
+                                guild.source_error = Some(error.clone());
+                                playback_errors.push(error);
+
+                                if autoplay && guild.pending_imports.is_empty() {
+                                    autoplay_tx = command_tx.clone();
+                                }
                             }
                             PlayMode::End => {
                                 guild.now_playing = None;
                                 guild.is_playing = false;
+
+                                // Unclassified output on End is usually just the pipe
+                                // closing on skip/stop, so only recognised failures count.
+                                if let Some(reason) = self.diagnostics.classify()
+                                    && !matches!(reason, SourceError::Other(_))
+                                {
+                                    guild.source_error = Some(reason.to_string());
+                                    playback_errors.push(reason.to_string());
+                                }
+
+                                // The bot checks the queue is really empty before picking a track
+                                if autoplay && guild.pending_imports.is_empty() {
+                                    autoplay_tx = command_tx.clone();
+                                }
                             }
                             _ => {}
                         }
@@ -125,16 +517,111 @@ impl EventHandler for TrackObserver {
             }
 
             for error in playback_errors {
-                app_state.system_logs.push(format!(
-                    "[{}] Playback Error in guild {}: {}",
-                    self.uuid, self.guild_id, error
+                app_state.log_entry(LogEntry::new(
+                    LogLevel::Error,
+                    Some(self.uuid.clone()),
+                    Some(self.guild_id),
+                    format!("Playback Error: {}", error),
                 ));
             }
+
+            if let Some(tx) = autoplay_tx {
+                let _ = tx.try_send(BotCommand::Autoplay {
+                    guild_id: self.guild_id,
+                });
+            }
         }
         None
     }
 }
 
+/// Reasons the pre-flight check refuses to join a voice channel.
+#[derive(Debug)]
+enum JoinProblem {
+    NotInGuild,
+    ChannelNotFound,
+    MissingConnect,
+    MissingSpeak,
+    ChannelFull { limit: u32 },
+}
+
+impl std::fmt::Display for JoinProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JoinProblem::NotInGuild => write!(f, "the bot is not a member of this server"),
+            JoinProblem::ChannelNotFound => write!(f, "the channel no longer exists"),
+            JoinProblem::MissingConnect => write!(f, "missing Connect permission"),
+            JoinProblem::MissingSpeak => write!(f, "missing Speak permission"),
+            JoinProblem::ChannelFull { limit } => {
+                write!(f, "the channel is full ({} user limit)", limit)
+            }
+        }
+    }
+}
+
+/// Whether the given permissions allow the bot to be heard in a channel of this type.
+///
+/// Stage channels need Request to Speak, or Mute Members to unsuppress itself.
+fn can_speak_in(kind: ChannelType, permissions: Permissions) -> bool {
+    match kind {
+        ChannelType::Stage => permissions.request_to_speak() || permissions.mute_members(),
+        _ => permissions.speak(),
+    }
+}
+
+/// The channel's user limit if it keeps the bot out. Only other users count against
+/// the limit, and Move Members lets the bot join full channels.
+fn full_channel_limit(
+    guild: &Guild,
+    channel: &GuildChannel,
+    bot_id: UserId,
+    permissions: Permissions,
+) -> Option<u32> {
+    let limit = channel.user_limit.filter(|l| *l > 0)?;
+    if permissions.move_members() {
+        return None;
+    }
+    let others = guild
+        .voice_states
+        .values()
+        .filter(|vs| vs.channel_id == Some(channel.id) && vs.user_id != bot_id)
+        .count();
+    (others >= limit as usize).then_some(limit)
+}
+
+/// How an imported playlist entry is listed until it is resolved: cached metadata if the
+/// URL played before, otherwise what the playlist says about it.
+fn pending_track(
+    entry: PlaylistEntry,
+    known: Option<CachedMetadata>,
+    requested_by: &str,
+) -> TrackMetadata {
+    let (title, artist, duration_secs, thumbnail_url) = match known {
+        Some(meta) => (
+            meta.title,
+            meta.artist,
+            meta.duration_secs.map(|secs| secs as u64),
+            meta.thumbnail_url,
+        ),
+        None => (
+            entry.title.unwrap_or_else(|| entry.location.clone()),
+            None,
+            entry.duration_secs,
+            None,
+        ),
+    };
+    TrackMetadata {
+        uuid: uuid::Uuid::new_v4().to_string(),
+        title,
+        artist,
+        url: entry.location,
+        duration_secs,
+        thumbnail_url,
+        added_by: requested_by.to_string(),
+        gain: None,
+    }
+}
+
 /// Represents a running instance of a Discord Bot.
 struct BotInstance {
     uuid: String,
@@ -143,20 +630,128 @@ struct BotInstance {
     resolver: SourceResolver,
     songbird: Option<Arc<songbird::Songbird>>,
     http: Option<Arc<Http>>,
+    cache: Option<Arc<Cache>>,
     track_lookup: HashMap<uuid::Uuid, TrackMetadata>,
+    /// When each connected guild's queue last became empty.
+    idle_since: HashMap<u64, Instant>,
+    /// When each connected guild's channel last lost its final listener.
+    alone_since: HashMap<u64, Instant>,
+    /// Guilds we asked to leave, so the resulting voice state update is not treated as a kick.
+    pending_leaves: HashSet<u64>,
+    announcements: AnnouncementLog,
+    /// Live ICY titles of radio tracks, written by their `IcyWatcher`.
+    stream_titles: StreamTitles,
+    player_messages: HashMap<u64, PlayerMessage>,
+    /// Guilds whose player message couldn't be posted, and when to try again.
+    player_post_failures: HashMap<u64, PostFailures>,
+    /// Imported playlist entries per guild, enqueued as the queue runs low so only the next
+    /// track is resolved ahead of time.
+    import_queue: HashMap<u64, VecDeque<TrackMetadata>>,
+    sleep_timers: HashMap<u64, ActiveSleepTimer>,
+    /// Guilds stopped since their last play request, whose ending tracks must not trigger autoplay.
+    stopped: HashSet<u64>,
+    /// Spoken messages waiting to be played, per guild.
+    speech_queues: HashMap<u64, SpeechQueue>,
+    /// What autoplay has picked per guild since the last play request.
+    autoplay_runs: HashMap<u64, AutoplayRun>,
+    /// Autoplay fallback playlists by source, so they aren't downloaded for every pick.
+    fallback_playlists: HashMap<String, CachedPlaylist>,
+}
+
+/// Autoplay's picks for a guild since the last play request.
+#[derive(Default)]
+struct AutoplayRun {
+    /// The latest pick, checked against the history on the next autoplay.
+    last: Option<String>,
+    /// Normalized URLs of picks that never showed up in the history, so are not picked again.
+    failed: HashSet<String>,
+    /// How many picks in a row failed.
+    failures: u32,
+}
+
+/// The entries of an autoplay fallback playlist, as last loaded.
+struct CachedPlaylist {
+    loaded_at: Instant,
+    locations: Vec<String>,
+}
+
+/// A pending sleep timer and what the tick checks it against.
+struct ActiveSleepTimer {
+    timer: SleepTimer,
+    /// When a `SleepAfter::Duration` timer fires.
+    deadline: Option<Instant>,
+    /// For `SleepAfter::AfterCurrentTrack`, the track to stop after.
+    track: Option<uuid::Uuid>,
+    /// Disarms the track's `PauseAtEnd` handler when the timer is cancelled.
+    armed: Arc<AtomicBool>,
+}
+
+/// Pauses the queue as soon as a track ends, so the next track isn't heard
+/// before the sleep timer stops playback on the next tick.
+struct PauseAtEnd {
+    call: Arc<tokio::sync::Mutex<songbird::Call>>,
+    armed: Arc<AtomicBool>,
+}
+
+#[async_trait::async_trait]
+impl EventHandler for PauseAtEnd {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if self.armed.load(Ordering::SeqCst) {
+            let _ = self.call.lock().await.queue().pause();
+        }
+        None
+    }
+}
+
+/// Consecutive failures to post a guild's player message.
+struct PostFailures {
+    channel_id: ChannelId,
+    count: u32,
+    retry_at: Instant,
+}
+
+/// A posted player message and the view it currently shows.
+struct PlayerMessage {
+    channel_id: ChannelId,
+    message_id: MessageId,
+    view: PlayerView,
 }
 
 impl BotInstance {
     /// Creates a new BotInstance.
     fn new(uuid: String, state: SharedState, cmd_rx: Receiver<BotCommand>) -> Self {
+        Self::with_resolver(uuid, state, cmd_rx, SourceResolver::new())
+    }
+
+    /// Creates a new BotInstance that plays through the given resolver.
+    fn with_resolver(
+        uuid: String,
+        state: SharedState,
+        cmd_rx: Receiver<BotCommand>,
+        resolver: SourceResolver,
+    ) -> Self {
         Self {
             uuid,
             state,
             cmd_rx,
-            resolver: SourceResolver::new(),
+            resolver,
             songbird: None,
             http: None,
+            cache: None,
             track_lookup: HashMap::new(),
+            idle_since: HashMap::new(),
+            alone_since: HashMap::new(),
+            pending_leaves: HashSet::new(),
+            announcements: AnnouncementLog::default(),
+            stream_titles: StreamTitles::default(),
+            player_messages: HashMap::new(),
+            player_post_failures: HashMap::new(),
+            import_queue: HashMap::new(),
+            sleep_timers: HashMap::new(),
+            stopped: HashSet::new(),
+            speech_queues: HashMap::new(),
+            autoplay_runs: HashMap::new(),
+            fallback_playlists: HashMap::new(),
         }
     }
 
@@ -167,13 +762,16 @@ impl BotInstance {
         let manager = songbird::Songbird::serenity();
         self.songbird = Some(manager.clone());
         let intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_VOICE_STATES;
+        let (event_tx, event_rx) = mpsc::channel(32);
 
         match Client::builder(&token, intents)
+            .event_handler(GatewayHandler { event_tx })
             .register_songbird_with(manager)
             .await
         {
             Ok(client) => {
                 self.http = Some(client.http.clone());
+                self.cache = Some(client.cache.clone());
 
                 if let Ok(info) = client.http.get_current_application_info().await {
                     let app_id = info.id.get();
@@ -181,21 +779,15 @@ impl BotInstance {
                         acc.application_id = Some(app_id);
                     });
                 } else {
-                    self.log(
+                    self.log_at(
+                        LogLevel::Warn,
+                        None,
                         "Failed to fetch Application ID. Invite functionality may be limited.",
                     );
                 }
 
-                if let Ok(guilds) = client.http.get_guilds(None, None).await {
-                    self.update_account(|acc| {
-                        acc.status = BotStatus::Online;
-                        for g in guilds {
-                            acc.guilds
-                                .entry(g.id.get())
-                                .or_insert_with(|| GuildState::new(g.id.get(), g.name));
-                        }
-                    });
-                }
+                self.fetch_guilds().await;
+                self.update_account(|acc| acc.status = BotStatus::Online);
 
                 let mut runner = client;
                 tokio::spawn(async move {
@@ -203,17 +795,17 @@ impl BotInstance {
                 });
 
                 self.log("Connected and Ready.");
-                self.command_loop().await;
+                self.command_loop(event_rx).await;
             }
             Err(e) => {
-                self.log(&format!("Connection Failed: {}", e));
+                self.log_at(LogLevel::Error, None, &format!("Connection Failed: {}", e));
                 self.update_account(|acc| acc.status = BotStatus::Error(e.to_string()));
             }
         }
     }
 
     /// The main event loop handling commands and periodic state sync.
-    async fn command_loop(&mut self) {
+    async fn command_loop(&mut self, mut event_rx: Receiver<GatewayEvent>) {
         let mut interval = tokio::time::interval(Duration::from_millis(500));
 
         loop {
@@ -227,8 +819,15 @@ impl BotInstance {
                         }
                     }
                 }
+                Some(event) = event_rx.recv() => {
+                    self.handle_gateway_event(event).await;
+                }
                 _ = interval.tick() => {
                     self.sync_state().await;
+                    self.check_auto_leave().await;
+                    self.update_player_messages().await;
+                    self.enqueue_next_import().await;
+                    self.check_sleep_timers().await;
                 }
             }
         }
@@ -242,45 +841,55 @@ impl BotInstance {
             BotCommand::Join {
                 guild_id,
                 channel_id,
+            } => self.join_channel(guild_id, channel_id).await,
+            BotCommand::Leave { guild_id } => self.leave_channel(guild_id).await,
+            BotCommand::Play {
+                guild_id,
+                url,
+                requested_by,
             } => {
-                if let Some(sb) = &self.songbird {
-                    if let Err(e) = sb
-                        .join(GuildId::new(guild_id), ChannelId::new(channel_id))
-                        .await
-                    {
-                        self.log(&format!("Failed to join channel: {}", e));
-                    }
-                }
+                self.autoplay_runs.remove(&guild_id);
+                self.play_track(guild_id, url, requested_by).await
             }
-            BotCommand::Leave { guild_id } => {
-                if let Some(sb) = &self.songbird {
-                    if let Err(e) = sb.leave(GuildId::new(guild_id)).await {
-                        self.log(&format!("Failed to leave channel: {}", e));
-                    }
-                    self.update_guild(guild_id, |g| g.channel_id = None);
-                }
+            BotCommand::Stop { guild_id } => {
+                self.cancel_imports(guild_id);
+                self.cancel_speech_resume(guild_id);
+                self.stopped.insert(guild_id);
+                self.call_control(guild_id, |q| q.stop());
             }
-            BotCommand::Play { guild_id, url } => self.play_track(guild_id, url).await,
-            BotCommand::Stop { guild_id } => self.call_control(guild_id, |q| q.stop()),
-            BotCommand::Skip { guild_id } => self.call_control(guild_id, |q| {
-                let _ = q.skip();
-            }),
-            BotCommand::Pause { guild_id } => self.call_control(guild_id, |q| {
-                let _ = q.pause();
-            }),
-            BotCommand::Resume { guild_id } => self.call_control(guild_id, |q| {
-                let _ = q.resume();
-            }),
-            BotCommand::Volume { guild_id, volume } => {
-                self.call_control(guild_id, move |q| {
-                    let _ = q.modify_queue(move |tracks| {
-                        for t in tracks {
-                            let _ = t.set_volume(volume);
-                        }
-                    });
-                });
-                self.update_guild(guild_id, |g| g.volume = volume);
+            BotCommand::Skip { guild_id } => {
+                self.cancel_speech_resume(guild_id);
+                self.call_control(guild_id, |q| {
+                    let _ = q.skip();
+                })
+            }
+            BotCommand::Pause { guild_id } => {
+                self.cancel_speech_resume(guild_id);
+                self.call_control(guild_id, |q| {
+                    let _ = q.pause();
+                })
+            }
+            BotCommand::Resume { guild_id } => {
+                self.cancel_speech_resume(guild_id);
+                self.call_control(guild_id, |q| {
+                    let _ = q.resume();
+                })
             }
+            BotCommand::Volume { guild_id, volume } => self.set_volume(guild_id, volume).await,
+            BotCommand::SetTrackGain {
+                guild_id,
+                track_uuid,
+                gain,
+            } => self.set_track_gain(guild_id, track_uuid, gain).await,
+            BotCommand::SetLoop { guild_id, enabled } => self.call_control(guild_id, move |q| {
+                if let Some(track) = q.current() {
+                    let _ = if enabled {
+                        track.enable_loop()
+                    } else {
+                        track.disable_loop()
+                    };
+                }
+            }),
             BotCommand::FetchChannels { guild_id } => self.fetch_channels(guild_id).await,
             BotCommand::RemoveTrack {
                 guild_id,
@@ -292,6 +901,7 @@ impl BotInstance {
                 to_index,
             } => self.move_track(guild_id, from_index, to_index).await,
             BotCommand::ClearQueue { guild_id } => {
+                self.cancel_imports(guild_id);
                 self.call_control(guild_id, |q| {
                     let _ = q.modify_queue(|deque| {
                         if deque.len() > 1 {
@@ -300,124 +910,1415 @@ impl BotInstance {
                     });
                 });
             }
+            BotCommand::ImportPlaylist {
+                guild_id,
+                source,
+                requested_by,
+            } => self.import_playlist(guild_id, source, requested_by).await,
+            BotCommand::ExportTracks {
+                guild_id,
+                which,
+                format,
+                path,
+            } => self.export_tracks(guild_id, which, format, path).await,
+            BotCommand::Say { guild_id, text } => self.say(guild_id, text),
+            BotCommand::SetSleepTimer {
+                guild_id,
+                after,
+                leave,
+            } => self.set_sleep_timer(guild_id, after, leave).await,
+            BotCommand::CancelSleepTimer { guild_id } => {
+                if self.cancel_sleep_timer(guild_id) {
+                    self.log_at(LogLevel::Info, Some(guild_id), "Sleep timer cancelled.");
+                }
+            }
+            BotCommand::PlaySound {
+                guild_id,
+                path,
+                volume,
+            } => self.play_sound(guild_id, path, volume).await,
+            BotCommand::Autoplay { guild_id } => self.autoplay(guild_id).await,
         }
     }
 
-    /// Resolves and plays a track from a URL.
-    ///
-    /// This method fetches metadata via the SourceResolver, creates a Songbird Track,
-    /// attaches event listeners for UI updates (e.g., track end), and enqueues it.
-    async fn play_track(&mut self, guild_id: u64, url: String) {
+    /// Starts (or replaces) a guild's sleep timer.
+    async fn set_sleep_timer(&mut self, guild_id: u64, after: SleepAfter, leave: bool) {
         let Some(sb) = &self.songbird else { return };
+        let Some(handler_lock) = sb.get(GuildId::new(guild_id)) else {
+            self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                "Not connected to a voice channel.",
+            );
+            return;
+        };
+        self.cancel_sleep_timer(guild_id);
 
-        if let Some(handler_lock) = sb.get(GuildId::new(guild_id)) {
-            let mut handler = handler_lock.lock().await;
-
-            match self.resolver.resolve(&url).await {
-                Ok(resolved) => {
-                    let track = songbird::tracks::Track::from(resolved.source);
-                    let handle = handler.enqueue(track).await;
-
-                    let metadata = TrackMetadata {
-                        uuid: uuid::Uuid::new_v4().to_string(),
-                        title: resolved.title.clone(),
-                        artist: None,
-                        url: url.clone(),
-                        duration_secs: resolved.duration.map(|d| d.as_secs()),
-                        thumbnail_url: None,
-                        added_by: "User".to_string(),
-                    };
+        let armed = Arc::new(AtomicBool::new(true));
+        let mut track = None;
+        let current = handler_lock.lock().await.queue().current();
+        let importing = self
+            .import_queue
+            .get(&guild_id)
+            .is_some_and(|q| !q.is_empty());
+        if after == SleepAfter::AfterQueue && current.is_none() && !importing {
+            self.log_at(LogLevel::Warn, Some(guild_id), "Nothing is playing.");
+            return;
+        }
+        if after == SleepAfter::AfterCurrentTrack {
+            let Some(current) = current else {
+                self.log_at(LogLevel::Warn, Some(guild_id), "Nothing is playing.");
+                return;
+            };
+            let pause = PauseAtEnd {
+                call: handler_lock.clone(),
+                armed: armed.clone(),
+            };
+            let _ = current.add_event(Event::Track(TrackEvent::End), pause);
+            track = Some(current.uuid());
+        }
 
-                    self.track_lookup.insert(handle.uuid(), metadata);
+        let (deadline, fires_at_ms) = match after {
+            SleepAfter::Duration(d) => (
+                Some(Instant::now() + d),
+                Some(Local::now().timestamp_millis() + d.as_millis() as i64),
+            ),
+            _ => (None, None),
+        };
+        let timer = SleepTimer {
+            after,
+            fires_at_ms,
+            leave,
+        };
 
-                    let observer = TrackObserver {
-                        uuid: self.uuid.clone(),
-                        guild_id,
-                        state: self.state.clone(),
-                    };
-                    let _ = handle.add_event(Event::Track(TrackEvent::End), observer);
+        self.log_at(
+            LogLevel::Info,
+            Some(guild_id),
+            &format!(
+                "Sleep timer set: stopping {}.",
+                timer.describe(Local::now().timestamp_millis())
+            ),
+        );
+        self.update_guild(guild_id, |g| g.sleep_timer = Some(timer.clone()));
+        self.sleep_timers.insert(
+            guild_id,
+            ActiveSleepTimer {
+                timer,
+                deadline,
+                track,
+                armed,
+            },
+        );
+    }
 
-                    let observer_err = TrackObserver {
-                        uuid: self.uuid.clone(),
-                        guild_id,
-                        state: self.state.clone(),
-                    };
-                    let _ = handle.add_event(Event::Track(TrackEvent::Error), observer_err);
+    /// Removes a guild's sleep timer, returning whether one was pending.
+    fn cancel_sleep_timer(&mut self, guild_id: u64) -> bool {
+        let Some(active) = self.sleep_timers.remove(&guild_id) else {
+            return false;
+        };
+        active.armed.store(false, Ordering::SeqCst);
+        self.update_guild(guild_id, |g| g.sleep_timer = None);
+        true
+    }
 
-                    self.log(&format!("Queued: {}", resolved.title));
+    /// Stops playback (and leaves, if asked) in guilds whose sleep timer has come due.
+    async fn check_sleep_timers(&mut self) {
+        let Some(sb) = self.songbird.clone() else {
+            return;
+        };
+
+        let mut due = Vec::new();
+        for (guild_id, active) in &self.sleep_timers {
+            // Without a call there is no track to wait on; leaving clears the timer
+            let Some(call) = sb.get(GuildId::new(*guild_id)) else {
+                continue;
+            };
+            let current = call.lock().await.queue().current().map(|t| t.uuid());
+            let fired = match active.timer.after {
+                SleepAfter::Duration(_) => active.deadline.is_some_and(|d| Instant::now() >= d),
+                SleepAfter::AfterCurrentTrack => current != active.track,
+                SleepAfter::AfterQueue => {
+                    current.is_none()
+                        && self.import_queue.get(guild_id).is_none_or(|q| q.is_empty())
                 }
-                Err(e) => self.log(&format!("Source Error: {}", e)),
+            };
+            if fired {
+                due.push((*guild_id, active.timer.leave));
+            }
+        }
+
+        for (guild_id, leave) in due {
+            self.cancel_sleep_timer(guild_id);
+            self.cancel_imports(guild_id);
+            self.stopped.insert(guild_id);
+            self.call_control(guild_id, |q| q.stop());
+            self.log_at(
+                LogLevel::Info,
+                Some(guild_id),
+                "Sleep timer: playback stopped.",
+            );
+            if leave {
+                self.leave_channel(guild_id).await;
             }
-        } else {
-            self.log("Error: Not connected to a voice channel.");
         }
     }
 
-    /// Removes a specific track from the queue based on its UUID.
-    async fn remove_track(&self, guild_id: u64, target_uuid: String) {
-        let Some(sb) = &self.songbird else { return };
-        if let Some(handler_lock) = sb.get(GuildId::new(guild_id)) {
+    /// Sets the guild volume on every queued track and remembers it for later tracks.
+    async fn set_volume(&mut self, guild_id: u64, volume: f32) {
+        let volume = volume.clamp(0.0, 1.0);
+        if let Some(sb) = &self.songbird
+            && let Some(handler_lock) = sb.get(GuildId::new(guild_id))
+        {
             let handler = handler_lock.lock().await;
-            let queue = handler.queue().current_queue();
-
-            for track in queue {
-                if let Some(meta) = self.track_lookup.get(&track.uuid()) {
-                    if meta.uuid == target_uuid {
-                        let _ = track.stop();
-                        self.log("Track removed from queue.");
-                        break;
-                    }
-                }
+            for track in handler.queue().current_queue() {
+                let track_volume = match self.track_lookup.get(&track.uuid()) {
+                    Some(meta) => meta.effective_volume(volume),
+                    None => volume,
+                };
+                let _ = track.set_volume(track_volume);
             }
         }
-    }
 
-    /// Moves a track within the queue.
-    ///
-    /// Indices are 0-based relative to the *visible* queue (excluding the currently playing track).
-    async fn move_track(&self, guild_id: u64, from: usize, to: usize) {
-        self.call_control(guild_id, move |q| {
-            let _ = q.modify_queue(move |deque| {
-                if deque.len() <= 1 {
-                    return;
-                }
+        self.update_account(|acc| {
+            acc.guild_settings.entry(guild_id).or_default().volume = volume;
+            if let Some(guild) = acc.guilds.get_mut(&guild_id) {
+                guild.volume = volume;
+            }
+        });
+        self.save_config();
+    }
 
-                // index 0 is Now Playing
-                let offset = 1;
-                let real_from = from + offset;
-                let real_to = to + offset;
+    /// Sets one queued track's gain and applies it immediately.
+    async fn set_track_gain(&mut self, guild_id: u64, track_uuid: String, gain: Option<f32>) {
+        let Some(sb) = &self.songbird else { return };
+        let Some(handler_lock) = sb.get(GuildId::new(guild_id)) else {
+            return;
+        };
+        let volume = self.guild_volume(guild_id);
+        let gain = gain.map(|g| g.clamp(0.0, 2.0));
 
-                if real_from < deque.len() && real_to < deque.len() {
-                    if let Some(item) = deque.remove(real_from) {
-                        deque.insert(real_to, item);
-                    }
-                }
-            });
-        });
+        let handler = handler_lock.lock().await;
+        for track in handler.queue().current_queue() {
+            if let Some(meta) = self.track_lookup.get_mut(&track.uuid())
+                && meta.uuid == track_uuid
+            {
+                meta.gain = gain;
+                let _ = track.set_volume(meta.effective_volume(volume));
+                break;
+            }
+        }
     }
 
-    /// Fetches the voice channels for a guild via the Discord API.
-    ///
-    /// This updates the shared state so the UI can populate the channel selector dropdown.
-    async fn fetch_channels(&self, guild_id: u64) {
-        let Some(http) = &self.http else { return };
-        if let Ok(channels) = http.get_channels(GuildId::new(guild_id)).await {
-            let voice_chans = channels
-                .into_iter()
-                .filter(|c| c.kind == ChannelType::Voice)
-                .map(|c| crate::state::NameId {
-                    id: c.id.get(),
-                    name: c.name,
-                })
-                .collect();
+    /// The remembered volume of a guild.
+    fn guild_volume(&self, guild_id: u64) -> f32 {
+        self.lock_state()
+            .accounts
+            .get(&self.uuid)
+            .map(|acc| acc.settings_for(guild_id).volume)
+            .unwrap_or(1.0)
+    }
 
-            self.update_guild(guild_id, |g| g.voice_channels = voice_chans);
+    /// Writes the current state to the config file, for settings changed from Discord.
+    fn save_config(&self) {
+        let cfg = ConfigManager::update_from_state(&self.lock_state());
+        if let Err(e) = ConfigManager::save(&cfg) {
+            self.log_at(
+                LogLevel::Error,
+                None,
+                &format!("Failed to save config: {}", e),
+            );
         }
     }
 
-    /// Helper to execute a closure against a guild's track queue safely.
-    fn call_control<F>(&self, guild_id: u64, f: F)
-    where
+    /// Plays a sound clip alongside the queue, so it mixes over whatever is playing.
+    async fn play_sound(&mut self, guild_id: u64, path: String, volume: f32) {
+        let Some(sb) = &self.songbird else { return };
+        let Some(handler_lock) = sb.get(GuildId::new(guild_id)) else {
+            self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                "Not connected to a voice channel.",
+            );
+            return;
+        };
+        // Anything else would fall through to yt-dlp or a search
+        if !Path::new(&path).is_file() {
+            self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                &format!("Sound file not found: {}", path),
+            );
+            return;
+        }
+
+        let Some((tools, audio_cache)) = self.start_resolving(guild_id) else {
+            return;
+        };
+        let result = self.resolver.resolve(&path, &tools, &audio_cache).await;
+        self.finish_resolving();
+        match result {
+            Ok(resolved) => {
+                let mut handler = handler_lock.lock().await;
+                let clip = handler.play_input(resolved.source);
+                let _ = clip.set_volume(volume);
+            }
+            Err(e) => self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                &format!("Failed to play sound {}: {}", path, e),
+            ),
+        }
+    }
+
+    /// Queues a message to be spoken in voice, pausing the current track until it ends.
+    fn say(&mut self, guild_id: u64, text: String) {
+        let Some(sb) = &self.songbird else { return };
+        if sb.get(GuildId::new(guild_id)).is_none() {
+            self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                "Not connected to a voice channel.",
+            );
+            return;
+        }
+
+        let queue = self.speech_queues.entry(guild_id).or_insert_with(|| {
+            SpeechQueue::start(self.uuid.clone(), guild_id, self.state.clone(), sb.clone())
+        });
+        queue.say(text);
+    }
+
+    /// Stops speech from resuming a track once the user controls playback.
+    fn cancel_speech_resume(&self, guild_id: u64) {
+        if let Some(queue) = self.speech_queues.get(&guild_id) {
+            queue.cancel_resume();
+        }
+    }
+
+    /// Queues a track related to the last one played, or the fallback playlist's
+    /// least recently played entry, once a guild's queue has run out.
+    ///
+    /// A pick that fails straight away never reaches the history, so it is skipped from
+    /// then on, and autoplay stops after `MAX_AUTOPLAY_FAILURES` such picks in a row.
+    async fn autoplay(&mut self, guild_id: u64) {
+        if self.stopped.contains(&guild_id) || self.import_queue.contains_key(&guild_id) {
+            return;
+        }
+        // A timer stopping after the track or queue expects playback to run out
+        if self
+            .sleep_timers
+            .get(&guild_id)
+            .is_some_and(|active| !matches!(active.timer.after, SleepAfter::Duration(_)))
+        {
+            return;
+        }
+        let Some(sb) = &self.songbird else { return };
+        let Some(handler_lock) = sb.get(GuildId::new(guild_id)) else {
+            return;
+        };
+        if !handler_lock.lock().await.queue().is_empty() {
+            return;
+        }
+
+        let (settings, seed, recent, tools, audio_cache) = {
+            let state = self.lock_state();
+            let Some(account) = state.accounts.get(&self.uuid) else {
+                return;
+            };
+            let history = account.guilds.get(&guild_id).map(|g| &g.history);
+            let recent: Vec<String> = history
+                .map(|h| {
+                    let skip = h.len().saturating_sub(AUTOPLAY_RECENT);
+                    h.iter().skip(skip).map(|t| normalize_url(&t.url)).collect()
+                })
+                .unwrap_or_default();
+            (
+                account.settings_for(guild_id),
+                history.and_then(|h| h.back()).map(|t| t.url.clone()),
+                recent,
+                state.tool_settings.clone(),
+                state.audio_cache_settings.clone(),
+            )
+        };
+        if !settings.autoplay {
+            return;
+        }
+
+        let run = self.autoplay_runs.entry(guild_id).or_default();
+        if let Some(last) = run.last.take() {
+            if recent.contains(&last) {
+                run.failures = 0;
+            } else {
+                run.failures += 1;
+                run.failed.insert(last);
+            }
+        }
+        if run.failures >= MAX_AUTOPLAY_FAILURES {
+            let failures = run.failures;
+            self.log_at(
+                LogLevel::Warn,
+                Some(guild_id),
+                &format!(
+                    "Autoplay: stopped after {} tracks in a row failed to play.",
+                    failures
+                ),
+            );
+            return;
+        }
+        let failed = run.failed.clone();
+        let is_recent = |url: &str| {
+            let key = normalize_url(url);
+            recent.contains(&key) || failed.contains(&key)
+        };
+
+        let mut next = None;
+        if let Some(seed) = &seed {
+            if self.start_resolving(guild_id).is_none() {
+                return;
+            }
+            let related = self.resolver.related(seed, &tools, &audio_cache).await;
+            self.finish_resolving();
+            match related {
+                Ok(related) => next = related.into_iter().map(|r| r.url).find(|u| !is_recent(u)),
+                Err(e) => self.log_at(
+                    LogLevel::Warn,
+                    Some(guild_id),
+                    &format!("Autoplay: no related tracks: {:#}", e),
+                ),
+            }
+        }
+        if next.is_none()
+            && let Some(fallback) = &settings.autoplay_fallback
+        {
+            match self.fallback_playlist(fallback).await {
+                // Never-played entries first, then whichever was played longest ago
+                Ok(locations) => {
+                    next = locations
+                        .into_iter()
+                        .filter(|location| !failed.contains(&normalize_url(location)))
+                        .min_by_key(|location| {
+                            let key = normalize_url(location);
+                            recent
+                                .iter()
+                                .rposition(|r| *r == key)
+                                .map_or(-1, |i| i as i64)
+                        })
+                }
+                Err(e) => self.log_at(
+                    LogLevel::Warn,
+                    Some(guild_id),
+                    &format!("Autoplay: fallback playlist failed: {:#}", e),
+                ),
+            }
+        }
+
+        match next {
+            Some(url) => {
+                self.log_at(
+                    LogLevel::Info,
+                    Some(guild_id),
+                    &format!("Autoplay: queueing {}", url),
+                );
+                if let Some(run) = self.autoplay_runs.get_mut(&guild_id) {
+                    run.last = Some(normalize_url(&url));
+                }
+                self.play_track(guild_id, url, "Autoplay".to_string()).await;
+            }
+            None => self.log_at(
+                LogLevel::Info,
+                Some(guild_id),
+                "Autoplay: nothing new to play.",
+            ),
+        }
+    }
+
+    /// The entries of an autoplay fallback playlist, loading it if it isn't cached or is stale.
+    async fn fallback_playlist(&mut self, source: &str) -> anyhow::Result<Vec<String>> {
+        if let Some(cached) = self.fallback_playlists.get(source)
+            && cached.loaded_at.elapsed() < FALLBACK_PLAYLIST_TTL
+        {
+            return Ok(cached.locations.clone());
+        }
+        let entries = playlist::load(source, &self.resolver.http_client()).await?;
+        let locations: Vec<String> = entries.into_iter().map(|e| e.location).collect();
+        self.fallback_playlists.insert(
+            source.to_string(),
+            CachedPlaylist {
+                loaded_at: Instant::now(),
+                locations: locations.clone(),
+            },
+        );
+        Ok(locations)
+    }
+
+    /// Loads a playlist and queues its entries for enqueueing.
+    async fn import_playlist(&mut self, guild_id: u64, source: String, requested_by: String) {
+        match playlist::load(&source, &self.resolver.http_client()).await {
+            Ok(entries) => {
+                self.log_at(
+                    LogLevel::Info,
+                    Some(guild_id),
+                    &format!("Importing {} tracks from {}", entries.len(), source),
+                );
+                let (tools, audio_cache) = {
+                    let state = self.lock_state();
+                    (
+                        state.tool_settings.clone(),
+                        state.audio_cache_settings.clone(),
+                    )
+                };
+                let mut tracks = VecDeque::with_capacity(entries.len());
+                for entry in entries {
+                    let known = self
+                        .resolver
+                        .metadata(&entry.location, &tools, &audio_cache)
+                        .await;
+                    tracks.push_back(pending_track(entry, known, &requested_by));
+                }
+
+                let pending = self.import_queue.entry(guild_id).or_default();
+                pending.extend(tracks);
+                let listed = pending.clone();
+                self.update_guild(guild_id, |g| g.pending_imports = listed);
+            }
+            Err(e) => {
+                self.log_at(LogLevel::Error, Some(guild_id), &format!("{:#}", e));
+                self.update_guild(guild_id, |g| g.source_error = Some(format!("{:#}", e)));
+            }
+        }
+    }
+
+    /// Writes a guild's queue or history to a playlist file and logs where it went.
+    async fn export_tracks(
+        &self,
+        guild_id: u64,
+        which: TrackList,
+        format: ExportFormat,
+        path: Option<PathBuf>,
+    ) {
+        let found = {
+            let state = self.lock_state();
+            state
+                .accounts
+                .get(&self.uuid)
+                .and_then(|a| a.guilds.get(&guild_id))
+                .map(|g| {
+                    (
+                        which.tracks(g),
+                        format!("{} {}", g.guild_name, which.label()),
+                    )
+                })
+        };
+        let Some((tracks, name)) = found.filter(|(tracks, _)| !tracks.is_empty()) else {
+            self.log_at(LogLevel::Warn, Some(guild_id), "Nothing to export.");
+            return;
+        };
+
+        let result = tokio::task::spawn_blocking(move || {
+            playlist::export(&tracks, &name, format, path.as_deref())
+        })
+        .await;
+        match result {
+            Ok(Ok(path)) => self.log_at(
+                LogLevel::Info,
+                Some(guild_id),
+                &format!("Exported tracks to {}", path.display()),
+            ),
+            Ok(Err(e)) => self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                &format!("Export failed: {:#}", e),
+            ),
+            Err(e) => self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                &format!("Export failed: {}", e),
+            ),
+        }
+    }
+
+    /// Resolves and enqueues the next imported entry of each guild whose queue has nothing
+    /// after the current track.
+    ///
+    /// Entries wait unresolved until then, so a long import doesn't start a yt-dlp download
+    /// per entry, while the next track is still ready before the current one ends.
+    /// Imports are dropped if the bot is no longer in a voice channel.
+    async fn enqueue_next_import(&mut self) {
+        let guilds: Vec<u64> = self.import_queue.keys().copied().collect();
+        for guild_id in guilds {
+            let call = self
+                .songbird
+                .as_ref()
+                .and_then(|sb| sb.get(GuildId::new(guild_id)));
+            let Some(call) = call else {
+                self.log_at(
+                    LogLevel::Warn,
+                    Some(guild_id),
+                    "Playlist import cancelled: not connected to a voice channel.",
+                );
+                self.cancel_imports(guild_id);
+                continue;
+            };
+            if call.lock().await.queue().len() > 1 {
+                continue;
+            }
+
+            let Some(pending) = self.import_queue.get_mut(&guild_id) else {
+                continue;
+            };
+            let next = pending.pop_front();
+            if pending.is_empty() {
+                self.import_queue.remove(&guild_id);
+            }
+            self.update_guild(guild_id, |g| {
+                g.pending_imports.pop_front();
+            });
+
+            if let Some(track) = next {
+                self.play_track(guild_id, track.url, track.added_by).await;
+            }
+        }
+    }
+
+    /// Drops any playlist entries not yet enqueued for a guild.
+    fn cancel_imports(&mut self, guild_id: u64) {
+        if self.import_queue.remove(&guild_id).is_some() {
+            self.update_guild(guild_id, |g| g.pending_imports.clear());
+        }
+    }
+
+    /// Fetches every guild the bot is in, paging through the API 200 guilds at a time.
+    ///
+    /// Guilds missing from the result are dropped; gateway events keep the list current afterwards.
+    async fn fetch_guilds(&self) {
+        let Some(http) = &self.http else { return };
+        let mut all = Vec::new();
+        let mut after = None;
+
+        loop {
+            match http
+                .get_guilds(after.map(GuildPagination::After), Some(200))
+                .await
+            {
+                Ok(page) => {
+                    let full_page = page.len() == 200;
+                    after = page.last().map(|g| g.id);
+                    all.extend(page);
+                    if !full_page {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    self.log_at(
+                        LogLevel::Error,
+                        None,
+                        &format!("Failed to fetch guild list: {}", e),
+                    );
+                    return;
+                }
+            }
+        }
+
+        self.update_account(|acc| {
+            acc.guilds
+                .retain(|id, _| all.iter().any(|g| g.id.get() == *id));
+            for g in all {
+                let volume = acc.settings_for(g.id.get()).volume;
+                acc.guilds.entry(g.id.get()).or_insert_with(|| {
+                    let mut guild = GuildState::new(g.id.get(), g.name);
+                    guild.volume = volume;
+                    guild
+                });
+            }
+        });
+    }
+
+    /// Dispatches events forwarded from the serenity gateway handler.
+    async fn handle_gateway_event(&mut self, event: GatewayEvent) {
+        match event {
+            GatewayEvent::BotVoiceStateUpdate {
+                guild_id,
+                old_channel_id,
+                channel_id,
+            } => {
+                self.on_voice_state_update(guild_id, old_channel_id, channel_id)
+                    .await
+            }
+            GatewayEvent::GuildAvailable { guild_id, name } => {
+                let mut added = false;
+                self.update_account(|acc| {
+                    let volume = acc.settings_for(guild_id).volume;
+                    acc.guilds.entry(guild_id).or_insert_with(|| {
+                        added = true;
+                        let mut guild = GuildState::new(guild_id, name.clone());
+                        guild.volume = volume;
+                        guild
+                    });
+                });
+                if added {
+                    self.log_at(
+                        LogLevel::Info,
+                        Some(guild_id),
+                        &format!("Joined guild: {}", name),
+                    );
+                }
+            }
+            GatewayEvent::GuildUpdated { guild_id, name } => {
+                self.update_guild(guild_id, |g| g.guild_name = name);
+            }
+            GatewayEvent::GuildRemoved { guild_id } => self.remove_guild(guild_id).await,
+            GatewayEvent::PlayerButton {
+                button,
+                interaction,
+            } => self.on_player_button(button, *interaction).await,
+        }
+    }
+
+    /// Handles a button press on a player message.
+    ///
+    /// The pressing user must pass `check_listener` before the mapped command runs;
+    /// otherwise they get an ephemeral explanation instead.
+    async fn on_player_button(&mut self, button: PlayerButton, interaction: ComponentInteraction) {
+        let Some(http) = self.http.clone() else {
+            return;
+        };
+        let guild_id = interaction.guild_id.map(|g| g.get());
+
+        let guild = guild_id.and_then(|id| {
+            let state = self.lock_state();
+            state.accounts.get(&self.uuid)?.guilds.get(&id).cloned()
+        });
+
+        let result = match &guild {
+            Some(guild) => self
+                .check_listener(guild, interaction.user.id)
+                .map(|()| button.command(guild)),
+            None => Err("This player is no longer active.".to_string()),
+        };
+
+        match result {
+            Ok(cmd) => {
+                self.log_at(
+                    LogLevel::Info,
+                    guild_id,
+                    &format!(
+                        "Player button {:?} pressed by {}.",
+                        button, interaction.user.name
+                    ),
+                );
+                self.handle_command(cmd).await;
+            }
+            Err(reason) => {
+                let reply = CreateInteractionResponseFollowup::new()
+                    .content(reason)
+                    .ephemeral(true);
+                let _ = interaction.create_followup(&http, reply).await;
+            }
+        }
+    }
+
+    /// Checks that a Discord user may control playback in a guild.
+    ///
+    /// Only users connected to the bot's voice channel may control it, so people
+    /// outside the channel can't skip or stop music for those listening.
+    fn check_listener(&self, guild: &GuildState, user_id: UserId) -> Result<(), String> {
+        let Some(bot_channel) = guild.channel_id else {
+            return Err("The bot is not in a voice channel.".to_string());
+        };
+        let user_channel = self.cache.as_ref().and_then(|cache| {
+            let g = cache.guild(GuildId::new(guild.guild_id))?;
+            g.voice_states.get(&user_id)?.channel_id
+        });
+
+        if user_channel.map(|c| c.get()) == Some(bot_channel) {
+            Ok(())
+        } else {
+            Err("Join the bot's voice channel to use the player.".to_string())
+        }
+    }
+
+    /// Posts, edits or removes each guild's player message to match its current state.
+    ///
+    /// Messages are only edited when the displayed view changes, keeping API calls
+    /// well under rate limits despite running every sync tick.
+    async fn update_player_messages(&mut self) {
+        let Some(http) = self.http.clone() else {
+            return;
+        };
+
+        let guilds: Vec<(u64, Option<u64>, Option<PlayerView>)> = {
+            let state = self.lock_state();
+            let Some(acc) = state.accounts.get(&self.uuid) else {
+                return;
+            };
+            acc.guilds
+                .values()
+                .map(|g| {
+                    let channel = acc.settings_for(g.guild_id).player_channel_id;
+                    let view = g.channel_id.map(|_| PlayerView::from_guild(g));
+                    (g.guild_id, channel, view)
+                })
+                .collect()
+        };
+
+        for (guild_id, channel, view) in guilds {
+            let existing = self.player_messages.remove(&guild_id);
+
+            let (Some(channel), Some(view)) = (channel.map(ChannelId::new), view) else {
+                if let Some(old) = existing {
+                    let _ = old.channel_id.delete_message(&http, old.message_id).await;
+                }
+                self.player_post_failures.remove(&guild_id);
+                continue;
+            };
+            // Wait out the backoff before posting again; a changed channel starts afresh
+            if existing.is_none()
+                && let Some(failures) = self.player_post_failures.get(&guild_id)
+                && failures.channel_id == channel
+                && Instant::now() < failures.retry_at
+            {
+                continue;
+            }
+
+            let message = match existing {
+                Some(old) if old.channel_id == channel && old.view == view => Some(old),
+                Some(old) if old.channel_id == channel => {
+                    let edit = EditMessage::new()
+                        .embed(view.embed())
+                        .components(view.components());
+                    // If the edit fails (e.g. the message was deleted), repost on the next tick.
+                    channel
+                        .edit_message(&http, old.message_id, edit)
+                        .await
+                        .ok()
+                        .map(|msg| PlayerMessage {
+                            channel_id: channel,
+                            message_id: msg.id,
+                            view,
+                        })
+                }
+                other => {
+                    if let Some(old) = other {
+                        let _ = old.channel_id.delete_message(&http, old.message_id).await;
+                    }
+                    let create = CreateMessage::new()
+                        .embed(view.embed())
+                        .components(view.components());
+                    match channel.send_message(&http, create).await {
+                        Ok(msg) => {
+                            self.player_post_failures.remove(&guild_id);
+                            Some(PlayerMessage {
+                                channel_id: channel,
+                                message_id: msg.id,
+                                view,
+                            })
+                        }
+                        Err(e) => {
+                            self.player_post_failed(guild_id, channel, e);
+                            None
+                        }
+                    }
+                }
+            };
+
+            if let Some(message) = message {
+                self.player_messages.insert(guild_id, message);
+            }
+        }
+    }
+
+    /// Schedules a retry after a failed player message post, doubling the delay each
+    /// time, and turns the player channel setting off after repeated failures.
+    fn player_post_failed(&mut self, guild_id: u64, channel_id: ChannelId, error: SerenityError) {
+        let count = match self.player_post_failures.get(&guild_id) {
+            Some(failures) if failures.channel_id == channel_id => failures.count + 1,
+            _ => 1,
+        };
+
+        if count >= MAX_PLAYER_POST_FAILURES {
+            self.player_post_failures.remove(&guild_id);
+            self.update_account(|acc| {
+                acc.guild_settings
+                    .entry(guild_id)
+                    .or_default()
+                    .player_channel_id = None;
+            });
+            self.save_config();
+            self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                &format!(
+                    "Player message disabled after {} failed attempts to post it: {}",
+                    count, error
+                ),
+            );
+            return;
+        }
+
+        let delay = PLAYER_RETRY_DELAY * 2u32.pow(count - 1);
+        self.player_post_failures.insert(
+            guild_id,
+            PostFailures {
+                channel_id,
+                count,
+                retry_at: Instant::now() + delay,
+            },
+        );
+        self.log_at(
+            LogLevel::Warn,
+            Some(guild_id),
+            &format!(
+                "Failed to post player message: {}. Retrying in {}s.",
+                error,
+                delay.as_secs()
+            ),
+        );
+    }
+
+    /// Forgets a guild the bot was removed from, dropping its call and UI selection.
+    async fn remove_guild(&mut self, guild_id: u64) {
+        if let Some(sb) = &self.songbird {
+            let _ = sb.remove(GuildId::new(guild_id)).await;
+        }
+        self.idle_since.remove(&guild_id);
+        self.alone_since.remove(&guild_id);
+        self.pending_leaves.remove(&guild_id);
+        self.player_messages.remove(&guild_id);
+        self.player_post_failures.remove(&guild_id);
+        self.import_queue.remove(&guild_id);
+        self.cancel_sleep_timer(guild_id);
+
+        let name = {
+            let mut state = self.lock_state();
+            if state.ui_context.selected_guild_id == Some(guild_id) {
+                state.ui_context.selected_guild_id = None;
+            }
+            state
+                .accounts
+                .get_mut(&self.uuid)
+                .and_then(|acc| acc.guilds.remove(&guild_id))
+                .map(|g| g.guild_name)
+        };
+
+        if let Some(name) = name {
+            self.log_at(
+                LogLevel::Info,
+                Some(guild_id),
+                &format!("Removed from guild: {}", name),
+            );
+        }
+    }
+
+    /// Reacts to the bot being moved or disconnected by someone else.
+    ///
+    /// `channel_id` is updated immediately rather than waiting for the next `sync_state` tick.
+    async fn on_voice_state_update(
+        &mut self,
+        guild_id: u64,
+        old_channel_id: Option<u64>,
+        channel_id: Option<u64>,
+    ) {
+        self.update_guild(guild_id, |g| g.channel_id = channel_id);
+
+        match (old_channel_id, channel_id) {
+            (Some(old), Some(new)) if old != new => {
+                self.log_at(
+                    LogLevel::Info,
+                    Some(guild_id),
+                    &format!("Moved from channel {} to {}.", old, new),
+                );
+            }
+            (Some(old), None) => {
+                if self.pending_leaves.remove(&guild_id) {
+                    return;
+                }
+
+                let settings = self
+                    .lock_state()
+                    .accounts
+                    .get(&self.uuid)
+                    .map(|a| a.settings_for(guild_id));
+                let settings = settings.unwrap_or_default();
+
+                self.cancel_speech_resume(guild_id);
+                if !settings.auto_rejoin {
+                    self.cancel_sleep_timer(guild_id);
+                }
+                let paused = match settings.on_disconnect {
+                    DisconnectAction::Pause => self.pause_playing_track(guild_id).await,
+                    DisconnectAction::Clear => {
+                        self.stopped.insert(guild_id);
+                        self.call_control(guild_id, |q| q.stop());
+                        None
+                    }
+                };
+                self.log_at(
+                    LogLevel::Warn,
+                    Some(guild_id),
+                    &format!(
+                        "Disconnected from channel {} by another user. Queue action: {:?}.",
+                        old, settings.on_disconnect
+                    ),
+                );
+
+                if settings.auto_rejoin {
+                    self.log_at(
+                        LogLevel::Info,
+                        Some(guild_id),
+                        &format!("Auto-rejoin: reconnecting to channel {}.", old),
+                    );
+                    self.join_channel(guild_id, old).await;
+                    let rejoined = self
+                        .lock_state()
+                        .accounts
+                        .get(&self.uuid)
+                        .and_then(|a| a.guilds.get(&guild_id))
+                        .is_some_and(|g| g.join_error.is_none());
+                    // A track that was already paused stays paused
+                    if rejoined && let Some(track) = paused {
+                        let _ = track.play();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Pauses the current track if it is playing, returning it so only this pause is undone.
+    async fn pause_playing_track(&self, guild_id: u64) -> Option<TrackHandle> {
+        let sb = self.songbird.as_ref()?;
+        let handler_lock = sb.get(GuildId::new(guild_id))?;
+        let track = handler_lock.lock().await.queue().current()?;
+        let info = track.get_info().await.ok()?;
+        if info.playing != PlayMode::Play {
+            return None;
+        }
+        let _ = track.pause();
+        Some(track)
+    }
+
+    /// Connects to a voice channel in a guild.
+    ///
+    /// Runs a permission pre-flight first so problems are reported precisely instead of
+    /// surfacing as a generic join timeout. Failures are shown on the guild's dashboard.
+    async fn join_channel(&mut self, guild_id: u64, channel_id: u64) {
+        let Some(sb) = self.songbird.clone() else {
+            return;
+        };
+
+        if let Err(problem) = self.preflight_join(guild_id, channel_id) {
+            self.log_at(
+                LogLevel::Warn,
+                Some(guild_id),
+                &format!("Cannot join channel {}: {}", channel_id, problem),
+            );
+            self.update_guild(guild_id, |g| g.join_error = Some(problem.to_string()));
+            return;
+        }
+
+        self.pending_leaves.remove(&guild_id);
+        if let Err(e) = sb
+            .join(GuildId::new(guild_id), ChannelId::new(channel_id))
+            .await
+        {
+            self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                &format!("Failed to join channel: {}", e),
+            );
+            self.update_guild(guild_id, |g| g.join_error = Some(e.to_string()));
+            return;
+        }
+        self.update_guild(guild_id, |g| g.join_error = None);
+
+        if self.cached_channel_kind(guild_id, channel_id) == Some(ChannelType::Stage) {
+            self.request_to_speak(guild_id, channel_id).await;
+        }
+    }
+
+    /// Disconnects from the voice channel in a guild.
+    async fn leave_channel(&mut self, guild_id: u64) {
+        let Some(sb) = &self.songbird else { return };
+        self.pending_leaves.insert(guild_id);
+        if let Err(e) = sb.leave(GuildId::new(guild_id)).await {
+            self.pending_leaves.remove(&guild_id);
+            self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                &format!("Failed to leave channel: {}", e),
+            );
+        }
+        self.update_guild(guild_id, |g| g.channel_id = None);
+        self.idle_since.remove(&guild_id);
+        self.alone_since.remove(&guild_id);
+        self.cancel_imports(guild_id);
+        self.cancel_sleep_timer(guild_id);
+    }
+
+    /// Resolves and plays a track from a URL.
+    ///
+    /// This method fetches metadata via the SourceResolver, creates a Songbird Track,
+    /// attaches event listeners for UI updates (e.g., track end), and enqueues it.
+    async fn play_track(&mut self, guild_id: u64, url: String, requested_by: String) {
+        self.stopped.remove(&guild_id);
+        let Some(sb) = self.songbird.clone() else {
+            return;
+        };
+        if sb.get(GuildId::new(guild_id)).is_none() {
+            self.log_at(
+                LogLevel::Error,
+                Some(guild_id),
+                "Not connected to a voice channel.",
+            );
+            return;
+        }
+
+        // The call stays unlocked while resolving, which can take seconds, so playback
+        // controls and speech aren't held up
+        let Some((tools, audio_cache)) = self.start_resolving(guild_id) else {
+            return;
+        };
+        let result = self.resolver.resolve(&url, &tools, &audio_cache).await;
+        self.finish_resolving();
+
+        match result {
+            Ok(resolved) => {
+                // The bot may have left the channel in the meantime
+                let Some(handler_lock) = sb.get(GuildId::new(guild_id)) else {
+                    return;
+                };
+                let track = songbird::tracks::Track::from(resolved.source)
+                    .volume(self.guild_volume(guild_id));
+                let handle = handler_lock.lock().await.enqueue(track).await;
+
+                let metadata = TrackMetadata {
+                    uuid: uuid::Uuid::new_v4().to_string(),
+                    title: resolved.title.clone(),
+                    artist: resolved.artist.clone(),
+                    url: resolved.url.clone(),
+                    duration_secs: resolved.duration.map(|d| d.as_secs()),
+                    thumbnail_url: resolved.thumbnail_url.clone(),
+                    added_by: requested_by,
+                    gain: None,
+                };
+
+                self.track_lookup.insert(handle.uuid(), metadata.clone());
+
+                let observer = TrackObserver {
+                    uuid: self.uuid.clone(),
+                    guild_id,
+                    state: self.state.clone(),
+                    diagnostics: resolved.diagnostics.clone(),
+                };
+                let _ = handle.add_event(Event::Track(TrackEvent::End), observer);
+
+                let observer_err = TrackObserver {
+                    uuid: self.uuid.clone(),
+                    guild_id,
+                    state: self.state.clone(),
+                    diagnostics: resolved.diagnostics.clone(),
+                };
+                let _ = handle.add_event(Event::Track(TrackEvent::Error), observer_err);
+
+                if let Some(http) = &self.http {
+                    let announcer = NowPlayingAnnouncer {
+                        uuid: self.uuid.clone(),
+                        guild_id,
+                        state: self.state.clone(),
+                        http: http.clone(),
+                        metadata: metadata.clone(),
+                        announcements: self.announcements.clone(),
+                        announced: AtomicBool::new(false),
+                    };
+                    let _ = handle.add_event(Event::Track(TrackEvent::Play), announcer);
+                }
+
+                let speaker = NextTrackSpeaker {
+                    uuid: self.uuid.clone(),
+                    guild_id,
+                    state: self.state.clone(),
+                    track_uuid: metadata.uuid.clone(),
+                };
+                let _ = handle.add_event(Event::Track(TrackEvent::End), speaker);
+
+                if resolved.icy_metadata {
+                    let watcher = IcyWatcher {
+                        uuid: self.uuid.clone(),
+                        guild_id,
+                        state: self.state.clone(),
+                        url: resolved.url.clone(),
+                        track_uuid: metadata.uuid.clone(),
+                        handle: handle.clone(),
+                        client: self.resolver.http_client(),
+                        titles: self.stream_titles.clone(),
+                        running: Arc::new(AtomicBool::new(false)),
+                    };
+                    let _ = handle.add_event(Event::Track(TrackEvent::Play), watcher);
+                }
+
+                self.log_at(
+                    LogLevel::Info,
+                    Some(guild_id),
+                    &format!("Queued: {}", resolved.title),
+                );
+                self.update_guild(guild_id, |g| g.source_error = None);
+            }
+            Err(e) => {
+                self.log_at(
+                    LogLevel::Error,
+                    Some(guild_id),
+                    &format!("Source Error: {}", e),
+                );
+                self.update_guild(guild_id, |g| g.source_error = Some(e.to_string()));
+                self.notify_guild(guild_id, &format!("Couldn't play <{}>: {}", url, e))
+                    .await;
+            }
+        }
+    }
+
+    /// Posts a plain message to the guild's announcement channel, if one is configured.
+    async fn notify_guild(&self, guild_id: u64, text: &str) {
+        let Some(http) = &self.http else { return };
+        let channel = self
+            .lock_state()
+            .accounts
+            .get(&self.uuid)
+            .and_then(|acc| acc.settings_for(guild_id).announce_channel_id);
+
+        if let Some(channel) = channel {
+            let message = CreateMessage::new().content(text);
+            let _ = ChannelId::new(channel).send_message(http, message).await;
+        }
+    }
+
+    /// Removes a specific track from the queue based on its UUID.
+    async fn remove_track(&self, guild_id: u64, target_uuid: String) {
+        let Some(sb) = &self.songbird else { return };
+        let Some(handler_lock) = sb.get(GuildId::new(guild_id)) else {
+            return;
+        };
+        let handler = handler_lock.lock().await;
+        let queue = handler.queue();
+        let Some(index) = queue.current_queue().iter().position(|track| {
+            self.track_lookup
+                .get(&track.uuid())
+                .is_some_and(|meta| meta.uuid == target_uuid)
+        }) else {
+            return;
+        };
+
+        if index == 0 {
+            // The queue moves on to the next track when the current one stops
+            if let Some(track) = queue.current() {
+                let _ = track.stop();
+            }
+        } else if let Some(track) = queue.dequeue(index) {
+            // A stopped track is only dropped by the queue once it reaches the front, so take
+            // upcoming ones out now
+            let _ = track.stop();
+        }
+        self.log_at(LogLevel::Info, Some(guild_id), "Track removed from queue.");
+    }
+
+    /// Moves a track within the queue.
+    ///
+    /// Indices are 0-based relative to the *visible* queue (excluding the currently playing track).
+    async fn move_track(&self, guild_id: u64, from: usize, to: usize) {
+        self.call_control(guild_id, move |q| {
+            let _ = q.modify_queue(move |deque| {
+                if deque.len() <= 1 {
+                    return;
+                }
+
+                // index 0 is Now Playing
+                let offset = 1;
+                let real_from = from + offset;
+                let real_to = to + offset;
+
+                if real_from < deque.len() && real_to < deque.len() {
+                    if let Some(item) = deque.remove(real_from) {
+                        deque.insert(real_to, item);
+                    }
+                }
+            });
+        });
+    }
+
+    /// Fetches the voice, stage and text channels for a guild via the Discord API.
+    ///
+    /// Occupancy, fullness and the bot's Connect/Speak permissions are filled in from the serenity cache.
+    /// This updates the shared state so the UI can populate the channel selector dropdown.
+    async fn fetch_channels(&self, guild_id: u64) {
+        let Some(http) = &self.http else { return };
+        let Ok(channels) = http.get_channels(GuildId::new(guild_id)).await else {
+            return;
+        };
+
+        let categories: HashMap<ChannelId, &GuildChannel> = channels
+            .iter()
+            .filter(|c| c.kind == ChannelType::Category)
+            .map(|c| (c.id, c))
+            .collect();
+
+        let mut voice_chans: Vec<_> = channels
+            .iter()
+            .filter_map(|c| {
+                let kind = match c.kind {
+                    ChannelType::Voice => VoiceChannelKind::Voice,
+                    ChannelType::Stage => VoiceChannelKind::Stage,
+                    _ => return None,
+                };
+                let category = c.parent_id.and_then(|id| categories.get(&id));
+                let permissions = self.bot_permissions(c);
+
+                // Discord lists uncategorized channels first, then by category position.
+                let sort_key = (category.map(|cat| (cat.position, cat.id)), c.position, c.id);
+                let channel = VoiceChannel {
+                    id: c.id.get(),
+                    name: c.name.clone(),
+                    kind,
+                    category: category.map(|cat| NameId {
+                        id: cat.id.get(),
+                        name: cat.name.clone(),
+                    }),
+                    position: c.position,
+                    user_limit: c.user_limit.filter(|l| *l > 0),
+                    member_count: self.count_voice_members(guild_id, c.id.get()),
+                    can_connect: permissions.is_none_or(|p| p.connect()),
+                    can_speak: permissions.is_none_or(|p| can_speak_in(c.kind, p)),
+                    is_full: self.is_channel_full(c),
+                };
+                Some((sort_key, channel))
+            })
+            .collect();
+
+        voice_chans.sort_by_key(|(key, _)| *key);
+        let voice_chans = voice_chans.into_iter().map(|(_, c)| c).collect();
+
+        let mut text_chans: Vec<_> = channels
+            .iter()
+            .filter(|c| matches!(c.kind, ChannelType::Text | ChannelType::News))
+            .collect();
+        text_chans.sort_by_key(|c| (c.position, c.id));
+        let text_chans = text_chans
+            .into_iter()
+            .map(|c| NameId {
+                id: c.id.get(),
+                name: c.name.clone(),
+            })
+            .collect();
+
+        self.update_guild(guild_id, |g| {
+            g.voice_channels = voice_chans;
+            g.text_channels = text_chans;
+        });
+    }
+
+    /// Asks to become a speaker after joining a stage channel.
+    ///
+    /// Unsuppressing succeeds outright if the bot has Mute Members; otherwise a request
+    /// to speak is raised for the stage moderators to approve.
+    async fn request_to_speak(&self, guild_id: u64, channel_id: u64) {
+        let Some(http) = &self.http else { return };
+        let target = (GuildId::new(guild_id), ChannelId::new(channel_id), None);
+
+        let unsuppress = EditVoiceState::new().suppress(false);
+        if unsuppress.execute(http.as_ref(), target).await.is_ok() {
+            self.log_at(
+                LogLevel::Info,
+                Some(guild_id),
+                "Joined stage channel as a speaker.",
+            );
+            return;
+        }
+
+        let request = EditVoiceState::new().request_to_speak(true);
+        match request.execute(http.as_ref(), target).await {
+            Ok(()) => self.log_at(
+                LogLevel::Info,
+                Some(guild_id),
+                "Joined stage channel and requested to speak.",
+            ),
+            Err(e) => self.log_at(
+                LogLevel::Warn,
+                Some(guild_id),
+                &format!("Failed to request to speak: {}", e),
+            ),
+        }
+    }
+
+    /// Checks from the serenity cache that the bot can join and be heard in a channel.
+    ///
+    /// If the cache or the guild isn't available the check is skipped and the join is attempted anyway.
+    fn preflight_join(&self, guild_id: u64, channel_id: u64) -> Result<(), JoinProblem> {
+        let Some(cache) = &self.cache else {
+            return Ok(());
+        };
+        let bot_id = cache.current_user().id;
+        let Some(guild) = cache.guild(GuildId::new(guild_id)) else {
+            return Ok(());
+        };
+        let member = guild.members.get(&bot_id).ok_or(JoinProblem::NotInGuild)?;
+        let channel = guild
+            .channels
+            .get(&ChannelId::new(channel_id))
+            .ok_or(JoinProblem::ChannelNotFound)?;
+
+        let permissions = guild.user_permissions_in(channel, member);
+        if !permissions.connect() {
+            return Err(JoinProblem::MissingConnect);
+        }
+        if !can_speak_in(channel.kind, permissions) {
+            return Err(JoinProblem::MissingSpeak);
+        }
+
+        if let Some(limit) = full_channel_limit(&guild, channel, bot_id, permissions) {
+            return Err(JoinProblem::ChannelFull { limit });
+        }
+
+        Ok(())
+    }
+
+    /// Computes the bot's effective permissions in a guild channel from the serenity cache.
+    ///
+    /// Returns `None` if the guild or the bot's member entry is not cached.
+    fn bot_permissions(&self, channel: &GuildChannel) -> Option<Permissions> {
+        let cache = self.cache.as_ref()?;
+        let bot_id = cache.current_user().id;
+        let guild = cache.guild(channel.guild_id)?;
+        let member = guild.members.get(&bot_id)?;
+        Some(guild.user_permissions_in(channel, member))
+    }
+
+    /// Whether the serenity cache shows the channel too full for the bot to join.
+    fn is_channel_full(&self, channel: &GuildChannel) -> bool {
+        let Some(cache) = &self.cache else {
+            return false;
+        };
+        let bot_id = cache.current_user().id;
+        let Some(guild) = cache.guild(channel.guild_id) else {
+            return false;
+        };
+        let Some(member) = guild.members.get(&bot_id) else {
+            return false;
+        };
+        let permissions = guild.user_permissions_in(channel, member);
+        full_channel_limit(&guild, channel, bot_id, permissions).is_some()
+    }
+
+    /// Looks up the type of a guild channel in the serenity cache.
+    fn cached_channel_kind(&self, guild_id: u64, channel_id: u64) -> Option<ChannelType> {
+        let cache = self.cache.as_ref()?;
+        let guild = cache.guild(GuildId::new(guild_id))?;
+        guild
+            .channels
+            .get(&ChannelId::new(channel_id))
+            .map(|c| c.kind)
+    }
+
+    /// Helper to execute a closure against a guild's track queue safely.
+    fn call_control<F>(&self, guild_id: u64, f: F)
+    where
         F: FnOnce(&songbird::tracks::TrackQueue) + Send + 'static,
     {
         if let Some(sb) = &self.songbird {
@@ -461,19 +2362,27 @@ impl BotInstance {
                 let mut now_playing_meta = None;
                 let mut is_playing = false;
                 let mut is_paused = false;
+                let mut is_looping = false;
                 let mut position = 0;
-                let mut volume = 1.0;
 
                 if let Some(track) = current_track_handle {
                     active_uuids.insert(track.uuid());
                     if let Ok(info) = track.get_info().await {
                         is_playing = info.playing == PlayMode::Play;
                         is_paused = info.playing == PlayMode::Pause;
+                        is_looping = info.loops == LoopState::Infinite;
                         position = info.position.as_secs();
-                        volume = info.volume;
 
                         if let Some(meta) = self.track_lookup.get(&track.uuid()) {
-                            now_playing_meta = Some(meta.clone());
+                            let mut meta = meta.clone();
+                            // Radio: show the song currently on air, with the station as artist
+                            let titles =
+                                self.stream_titles.lock().unwrap_or_else(|e| e.into_inner());
+                            if let Some(title) = titles.get(&meta.uuid) {
+                                meta.artist =
+                                    Some(std::mem::replace(&mut meta.title, title.clone()));
+                            }
+                            now_playing_meta = Some(meta);
                         }
                     }
                 }
@@ -494,8 +2403,17 @@ impl BotInstance {
                 self.update_guild(guild_id, |g| {
                     g.is_playing = is_playing;
                     g.is_paused = is_paused;
+                    g.is_looping = is_looping;
                     g.position_secs = position;
-                    g.volume = volume;
+                    // A new track started playing; looping keeps the same track, so it isn't repeated
+                    if let Some(meta) = &now_playing_meta
+                        && g.history.back().is_none_or(|last| last.uuid != meta.uuid)
+                    {
+                        g.history.push_back(meta.clone());
+                        if g.history.len() > MAX_HISTORY {
+                            g.history.pop_front();
+                        }
+                    }
                     g.now_playing = now_playing_meta;
                     g.queue = new_queue;
                     g.channel_id = channel_id;
@@ -506,6 +2424,125 @@ impl BotInstance {
         self.track_lookup.retain(|k, _| active_uuids.contains(k));
     }
 
+    /// Leaves voice channels that have been idle or empty for longer than the guild allows.
+    ///
+    /// Idle means the queue is empty; empty means no non-bot users remain in the channel.
+    /// Occupancy is read from the serenity cache, which is kept current by voice state updates.
+    async fn check_auto_leave(&mut self) {
+        let Some(sb) = self.songbird.clone() else {
+            return;
+        };
+
+        let guilds: Vec<(u64, GuildSettings)> = {
+            let state = self.lock_state();
+            let Some(acc) = state.accounts.get(&self.uuid) else {
+                return;
+            };
+            acc.guilds
+                .keys()
+                .map(|id| (*id, acc.settings_for(*id)))
+                .collect()
+        };
+
+        let now = Instant::now();
+
+        for (guild_id, settings) in guilds {
+            let Some(call_lock) = sb.get(GuildId::new(guild_id)) else {
+                continue;
+            };
+            let (channel, queue_empty) = {
+                let call = call_lock.lock().await;
+                (call.current_channel(), call.queue().is_empty())
+            };
+
+            let Some(channel) = channel else {
+                self.idle_since.remove(&guild_id);
+                self.alone_since.remove(&guild_id);
+                continue;
+            };
+
+            let idle_for = if queue_empty {
+                Some(now - *self.idle_since.entry(guild_id).or_insert(now))
+            } else {
+                self.idle_since.remove(&guild_id);
+                None
+            };
+
+            let alone_for = if self.count_listeners(guild_id, channel.0.get()) == Some(0) {
+                Some(now - *self.alone_since.entry(guild_id).or_insert(now))
+            } else {
+                self.alone_since.remove(&guild_id);
+                None
+            };
+
+            let exceeded = |elapsed: Option<Duration>, limit_mins: Option<u64>| {
+                elapsed
+                    .zip(limit_mins)
+                    .is_some_and(|(e, m)| e >= Duration::from_secs(m * 60))
+            };
+
+            let reason = if exceeded(alone_for, settings.alone_timeout_mins) {
+                Some("no listeners remained in the channel")
+            } else if exceeded(idle_for, settings.idle_timeout_mins) {
+                Some("the queue was empty")
+            } else {
+                None
+            };
+
+            if let Some(reason) = reason {
+                self.leave_channel(guild_id).await;
+                self.log_at(
+                    LogLevel::Info,
+                    Some(guild_id),
+                    &format!("Auto-leave: disconnected because {}.", reason),
+                );
+            }
+        }
+    }
+
+    /// Counts every user (including bots) connected to a voice channel using the serenity cache.
+    fn count_voice_members(&self, guild_id: u64, channel_id: u64) -> usize {
+        let Some(cache) = &self.cache else { return 0 };
+        let Some(guild) = cache.guild(GuildId::new(guild_id)) else {
+            return 0;
+        };
+        guild
+            .voice_states
+            .values()
+            .filter(|vs| vs.channel_id.map(|c| c.get()) == Some(channel_id))
+            .count()
+    }
+
+    /// Counts the non-bot users in a voice channel using the serenity cache.
+    ///
+    /// Returns `None` if the guild is not cached yet, so an unknown occupancy is never treated as empty.
+    fn count_listeners(&self, guild_id: u64, channel_id: u64) -> Option<usize> {
+        let cache = self.cache.as_ref()?;
+        let bot_id = cache.current_user().id;
+        let guild = cache.guild(GuildId::new(guild_id))?;
+
+        let count = guild
+            .voice_states
+            .values()
+            .filter(|vs| vs.channel_id.map(|c| c.get()) == Some(channel_id))
+            .filter(|vs| vs.user_id != bot_id)
+            .filter(|vs| {
+                // Voice states don't always carry the member, so fall back to the cached user
+                let member = vs
+                    .member
+                    .as_ref()
+                    .or_else(|| guild.members.get(&vs.user_id));
+                let is_bot = match member {
+                    Some(member) => member.user.bot,
+                    None => cache.user(vs.user_id).is_some_and(|user| user.bot),
+                };
+                !is_bot
+            })
+            .count();
+
+        Some(count)
+    }
+
     /// Thread-safe helper to update the account state.
     fn update_account<F>(&self, f: F)
     where
@@ -530,14 +2567,160 @@ impl BotInstance {
         }
     }
 
+    /// Counts a resolve that may run yt-dlp, so yt-dlp isn't updated underneath it, and returns
+    /// the settings to resolve with. Refused with a warning while yt-dlp is updating.
+    ///
+    /// Each successful call must be paired with `finish_resolving`.
+    fn start_resolving(&self, guild_id: u64) -> Option<(ToolSettings, AudioCacheSettings)> {
+        {
+            let mut state = self.lock_state();
+            if !state.yt_dlp_updating {
+                state.resolving_tracks += 1;
+                return Some((
+                    state.tool_settings.clone(),
+                    state.audio_cache_settings.clone(),
+                ));
+            }
+        }
+        self.log_at(
+            LogLevel::Warn,
+            Some(guild_id),
+            "yt-dlp is updating; try again shortly.",
+        );
+        None
+    }
+
+    /// Ends a resolve counted by `start_resolving`.
+    fn finish_resolving(&self) {
+        self.lock_state().resolving_tracks -= 1;
+    }
+
     /// Acquires a lock on the global state.
     fn lock_state(&self) -> MutexGuard<'_, crate::state::AppState> {
         self.state.lock().unwrap_or_else(|e| e.into_inner())
     }
 
-    /// Logs a message to the shared system logs.
+    /// Logs an info message for this account to the shared system logs.
     fn log(&self, msg: &str) {
-        let mut s = self.lock_state();
-        s.log(&format!("[{}] {}", self.uuid, msg));
+        self.log_at(LogLevel::Info, None, msg);
+    }
+
+    /// Logs a message for this account, optionally scoped to a guild.
+    fn log_at(&self, level: LogLevel, guild_id: Option<u64>, msg: &str) {
+        let entry = LogEntry::new(level, Some(self.uuid.clone()), guild_id, msg);
+        self.lock_state().log_entry(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::ToneProvider;
+    use std::sync::Mutex;
+
+    const GUILD: u64 = 1;
+
+    /// A bot that only plays test tones, with an unconnected call in `GUILD`.
+    fn tone_bot() -> BotInstance {
+        let mut account = AccountState::new("bot".to_string(), "Bot".to_string(), String::new());
+        account
+            .guilds
+            .insert(GUILD, GuildState::new(GUILD, "Test".to_string()));
+        let mut state = AppState::default();
+        state.accounts.insert("bot".to_string(), account);
+
+        let (_tx, rx) = mpsc::channel(1);
+        let resolver = SourceResolver::with_providers(vec![Box::new(ToneProvider)]);
+        let mut bot = BotInstance::with_resolver(
+            "bot".to_string(),
+            Arc::new(Mutex::new(state)),
+            rx,
+            resolver,
+        );
+
+        let manager = songbird::Songbird::serenity();
+        manager.initialise_client_data(1, UserId::new(1));
+        manager.get_or_insert(GuildId::new(GUILD));
+        bot.songbird = Some(manager);
+        bot
+    }
+
+    /// Titles of the call's queue, current track first, once the check passes.
+    ///
+    /// Queue commands apply on spawned tasks, so the queue is polled until they have run.
+    async fn queue_titles(bot: &BotInstance, check: impl Fn(&[String]) -> bool) -> Vec<String> {
+        let call = bot
+            .songbird
+            .as_ref()
+            .unwrap()
+            .get(GuildId::new(GUILD))
+            .unwrap();
+        for _ in 0..100 {
+            let titles: Vec<String> = call
+                .lock()
+                .await
+                .queue()
+                .current_queue()
+                .iter()
+                .map(|track| bot.track_lookup[&track.uuid()].title.clone())
+                .collect();
+            if check(&titles) {
+                return titles;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("queue never matched");
+    }
+
+    fn tone(hz: u32) -> String {
+        format!("Test tone ({} Hz)", hz)
+    }
+
+    // Without a voice connection Songbird never plays the tones, so loop state (reported
+    // by the mixer) can't be checked here; the queue itself is.
+    #[tokio::test]
+    async fn queue_commands_reorder_remove_and_clear_tones() {
+        let mut bot = tone_bot();
+        for hz in [220, 440, 880, 1760] {
+            bot.handle_command(BotCommand::Play {
+                guild_id: GUILD,
+                url: format!("tone://{}?secs=1", hz),
+                requested_by: "tester".to_string(),
+            })
+            .await;
+        }
+        let titles = queue_titles(&bot, |_| true).await;
+        assert_eq!(titles, [tone(220), tone(440), tone(880), tone(1760)]);
+        let added_by = bot.track_lookup.values().map(|t| t.added_by.as_str());
+        assert!(added_by.into_iter().all(|name| name == "tester"));
+
+        // Indices exclude the current track, as in the queue table
+        bot.handle_command(BotCommand::MoveTrack {
+            guild_id: GUILD,
+            from_index: 2,
+            to_index: 0,
+        })
+        .await;
+        let titles = queue_titles(&bot, |t| t[1] == tone(1760)).await;
+        assert_eq!(titles, [tone(220), tone(1760), tone(440), tone(880)]);
+
+        let removed = bot
+            .track_lookup
+            .values()
+            .find(|t| t.title == tone(440))
+            .map(|t| t.uuid.clone())
+            .unwrap();
+        bot.handle_command(BotCommand::RemoveTrack {
+            guild_id: GUILD,
+            track_uuid: removed,
+        })
+        .await;
+        let titles = queue_titles(&bot, |t| t.len() == 3).await;
+        assert_eq!(titles, [tone(220), tone(1760), tone(880)]);
+
+        bot.handle_command(BotCommand::ClearQueue { guild_id: GUILD })
+            .await;
+        let titles = queue_titles(&bot, |t| t.len() == 1).await;
+        assert_eq!(titles, [tone(220)]);
     }
 }
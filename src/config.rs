@@ -3,7 +3,11 @@
 //! Handles persisting app configuration.
 //! Configuration is stored in a `config.json` file located in the same directory as the executable.
 
-use crate::state::{AccountState, AppState, BotStatus};
+use crate::scheduler::ScheduledJob;
+use crate::state::{
+    AccountState, AppState, AudioCacheSettings, BotStatus, GuildSettings, SoundClip,
+    SpeechSettings, ToolSettings,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
@@ -17,6 +21,8 @@ pub struct SavedAccount {
     pub alias: String,
     pub token: String,
     pub auto_start: bool,
+    #[serde(default)]
+    pub guild_settings: HashMap<u64, GuildSettings>,
 }
 
 /// The top-level configuration file structure.
@@ -24,18 +30,33 @@ pub struct SavedAccount {
 pub struct AppConfig {
     pub accounts: Vec<SavedAccount>,
     pub last_selected_account: Option<String>,
+    #[serde(default)]
+    pub tools: ToolSettings,
+    #[serde(default)]
+    pub audio_cache: AudioCacheSettings,
+    #[serde(default)]
+    pub speech: SpeechSettings,
+    #[serde(default)]
+    pub soundboard: Vec<SoundClip>,
+    #[serde(default)]
+    pub schedule: Vec<ScheduledJob>,
 }
 
 /// Manages loading and saving of the application configuration.
 pub struct ConfigManager;
 
 impl ConfigManager {
-    /// Resolves the config file path relative to the executable location.
-    fn get_config_path() -> PathBuf {
+    /// Resolves the directory containing the executable, where app data is stored.
+    pub fn data_dir() -> PathBuf {
         env::current_exe()
             .ok()
-            .and_then(|p| p.parent().map(|p| p.join("config.json")))
-            .unwrap_or_else(|| PathBuf::from("config.json"))
+            .and_then(|p| p.parent().map(PathBuf::from))
+            .unwrap_or_default()
+    }
+
+    /// Resolves the config file path relative to the executable location.
+    fn get_config_path() -> PathBuf {
+        Self::data_dir().join("config.json")
     }
 
     /// Loads the configuration from disk. Returns default if file is missing or invalid.
@@ -66,6 +87,11 @@ impl ConfigManager {
         let mut state = AppState::default();
 
         state.ui_context.selected_account_uuid = config.last_selected_account.clone();
+        state.tool_settings = config.tools.clone();
+        state.audio_cache_settings = config.audio_cache.clone();
+        state.speech_settings = config.speech.clone();
+        state.soundboard = config.soundboard.clone();
+        state.scheduled_jobs = config.schedule.clone();
 
         for saved in &config.accounts {
             let account = AccountState {
@@ -76,6 +102,7 @@ impl ConfigManager {
                 auto_start: saved.auto_start,
                 status: BotStatus::Offline,
                 guilds: HashMap::new(),
+                guild_settings: saved.guild_settings.clone(),
                 command_tx: None,
             };
             state.accounts.insert(saved.uuid.clone(), account);
@@ -94,6 +121,7 @@ impl ConfigManager {
                 alias: acc.alias.clone(),
                 token: acc.token.clone(),
                 auto_start: acc.auto_start,
+                guild_settings: acc.guild_settings.clone(),
             })
             .collect();
 
@@ -103,6 +131,11 @@ impl ConfigManager {
         AppConfig {
             accounts,
             last_selected_account: state.ui_context.selected_account_uuid.clone(),
+            tools: state.tool_settings.clone(),
+            audio_cache: state.audio_cache_settings.clone(),
+            speech: state.speech_settings.clone(),
+            soundboard: state.soundboard.clone(),
+            schedule: state.scheduled_jobs.clone(),
         }
     }
 }
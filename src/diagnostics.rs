@@ -0,0 +1,291 @@
+//! Dependency Diagnostics Module
+//!
+//! Locates the external `yt-dlp` and `ffmpeg` binaries, reports their versions,
+//! and checks that ffmpeg supports the formats and protocols streaming relies on.
+//! Runs at startup, on demand from the GUI, and headless via `--check`.
+//! Also drives yt-dlp's self-update, since it breaks whenever sites change.
+
+use crate::sources::{background_command, bundled_bin_dir};
+use crate::state::ToolSettings;
+use anyhow::{Context, Result, bail};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// yt-dlp releases older than this are flagged, since site changes break old versions quickly.
+const YT_DLP_MAX_AGE_DAYS: i64 = 60;
+
+/// Demuxers needed for the formats yt-dlp typically selects (webm/opus, m4a, HLS, ogg, mp3).
+const REQUIRED_DEMUXERS: &[&str] = &["matroska", "mov", "hls", "ogg", "mp3"];
+
+/// Protocols needed to read remote streams.
+const REQUIRED_PROTOCOLS: &[&str] = &["http", "https"];
+
+/// Health of a single external binary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryStatus {
+    pub name: String,
+    pub path: Option<PathBuf>,
+    pub version: Option<String>,
+    /// Human-readable problems; empty means healthy.
+    pub problems: Vec<String>,
+}
+
+impl BinaryStatus {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Result of a full dependency check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyReport {
+    pub yt_dlp: BinaryStatus,
+    pub ffmpeg: BinaryStatus,
+    /// Local time the check ran, formatted for display.
+    pub checked_at: String,
+}
+
+impl DependencyReport {
+    pub fn is_ok(&self) -> bool {
+        self.yt_dlp.is_ok() && self.ffmpeg.is_ok()
+    }
+
+    /// Renders the report as plain text lines for the log and the console.
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for status in [&self.yt_dlp, &self.ffmpeg] {
+            let location = status
+                .path
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "not found".to_string());
+            lines.push(format!(
+                "{}: {} ({})",
+                status.name,
+                status.version.as_deref().unwrap_or("unknown version"),
+                location
+            ));
+            for problem in &status.problems {
+                lines.push(format!("  - {}", problem));
+            }
+        }
+        lines
+    }
+}
+
+/// Runs every check. Blocking: spawns the binaries and waits for them.
+pub fn check_dependencies(tools: &ToolSettings) -> DependencyReport {
+    DependencyReport {
+        yt_dlp: check_yt_dlp(tools.yt_dlp_path.as_deref()),
+        ffmpeg: check_ffmpeg(tools.ffmpeg_path.as_deref()),
+        checked_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    }
+}
+
+/// Finds a binary at its configured path, or else in the bundled `bin/` directory, then on PATH.
+///
+/// Returns the location searched as an error when nothing is found.
+pub fn locate_binary(name: &str, configured: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(configured) = configured {
+        let path = PathBuf::from(configured);
+        return if path.is_file() {
+            Ok(path)
+        } else {
+            Err(format!("configured path {}", path.display()))
+        };
+    }
+
+    let file_name = executable_name(name);
+    let path_dirs = env::var_os("PATH")
+        .map(|p| env::split_paths(&p).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    bundled_bin_dir()
+        .into_iter()
+        .chain(path_dirs)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| "bin/ or on PATH".to_string())
+}
+
+/// The platform file name of an executable.
+fn executable_name(name: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Starts a status for a binary, recording a problem if it cannot be found.
+fn locate_status(name: &str, configured: Option<&str>) -> BinaryStatus {
+    let mut status = BinaryStatus {
+        name: name.to_string(),
+        path: None,
+        version: None,
+        problems: Vec::new(),
+    };
+    match locate_binary(name, configured) {
+        Ok(path) => status.path = Some(path),
+        Err(searched) => status
+            .problems
+            .push(format!("{} was not found in {}.", name, searched)),
+    }
+    status
+}
+
+/// Runs a binary with arguments and returns its stdout, or `None` if it failed.
+fn run(path: &Path, args: &[&str]) -> Option<String> {
+    let output = background_command(path).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn check_yt_dlp(configured: Option<&str>) -> BinaryStatus {
+    let mut status = locate_status("yt-dlp", configured);
+    let Some(path) = status.path.clone() else {
+        return status;
+    };
+
+    match yt_dlp_version(&path) {
+        Some(version) => {
+            if let Some(age) = release_age_days(&version)
+                && age > YT_DLP_MAX_AGE_DAYS
+            {
+                status.problems.push(format!(
+                    "yt-dlp {} is {} days old; sites may have changed since. Consider updating.",
+                    version, age
+                ));
+            }
+            status.version = Some(version);
+        }
+        None => status
+            .problems
+            .push("yt-dlp is present but failed to report its version.".to_string()),
+    }
+    status
+}
+
+/// Reports the version of the yt-dlp at `path`.
+fn yt_dlp_version(path: &Path) -> Option<String> {
+    run(path, &["--version"]).map(|out| out.trim().to_string())
+}
+
+/// Versions before and after a yt-dlp self-update.
+#[derive(Debug, Clone)]
+pub struct UpdateOutcome {
+    pub path: PathBuf,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+}
+
+impl UpdateOutcome {
+    pub fn changed(&self) -> bool {
+        self.old_version != self.new_version
+    }
+}
+
+/// Runs `yt-dlp -U` against the configured yt-dlp, or the bundled `bin/` copy.
+///
+/// Copies installed by a package manager can't update themselves, so PATH is not searched.
+/// Blocking: waits for the download to finish.
+pub fn update_yt_dlp(tools: &ToolSettings) -> Result<UpdateOutcome> {
+    let path = match &tools.yt_dlp_path {
+        Some(configured) => PathBuf::from(configured),
+        None => bundled_bin_dir()
+            .context("Could not locate the executable directory")?
+            .join(executable_name("yt-dlp")),
+    };
+    if !path.is_file() {
+        bail!("No yt-dlp to update at {}", path.display());
+    }
+
+    let old_version = yt_dlp_version(&path);
+    let output = background_command(&path)
+        .arg("-U")
+        .output()
+        .with_context(|| format!("Failed to run {}", path.display()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "yt-dlp update failed: {}",
+            stderr.lines().last().unwrap_or("unknown error")
+        );
+    }
+
+    Ok(UpdateOutcome {
+        new_version: yt_dlp_version(&path),
+        old_version,
+        path,
+    })
+}
+
+/// Age in days of a date-based yt-dlp version such as `2024.08.06` (or `2024.08.06.1`).
+fn release_age_days(version: &str) -> Option<i64> {
+    let date_part: Vec<&str> = version.split('.').take(3).collect();
+    let date = NaiveDate::parse_from_str(&date_part.join("."), "%Y.%m.%d").ok()?;
+    Some((Local::now().date_naive() - date).num_days())
+}
+
+fn check_ffmpeg(configured: Option<&str>) -> BinaryStatus {
+    let mut status = locate_status("ffmpeg", configured);
+    let Some(path) = status.path.clone() else {
+        return status;
+    };
+
+    // First line looks like "ffmpeg version 6.1.1 Copyright (c) ..."
+    status.version = run(&path, &["-hide_banner", "-version"]).and_then(|out| {
+        out.lines()
+            .next()
+            .and_then(|l| l.strip_prefix("ffmpeg version "))
+            .and_then(|rest| rest.split_whitespace().next())
+            .map(str::to_string)
+    });
+    if status.version.is_none() {
+        status
+            .problems
+            .push("ffmpeg is present but failed to report its version.".to_string());
+        return status;
+    }
+
+    let demuxers = run(&path, &["-hide_banner", "-demuxers"]).unwrap_or_default();
+    for name in REQUIRED_DEMUXERS {
+        if !lists_component(&demuxers, name) {
+            status
+                .problems
+                .push(format!("ffmpeg is missing the '{}' demuxer.", name));
+        }
+    }
+
+    let protocols = run(&path, &["-hide_banner", "-protocols"]).unwrap_or_default();
+    let input_protocols = protocols
+        .split("Output:")
+        .next()
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    for name in REQUIRED_PROTOCOLS {
+        if !input_protocols.contains(name) {
+            status
+                .problems
+                .push(format!("ffmpeg is missing the '{}' input protocol.", name));
+        }
+    }
+    status
+}
+
+/// Whether an `ffmpeg -demuxers` listing includes a component.
+///
+/// Lines look like ` D  matroska,webm   Matroska / WebM`; names may be comma-joined.
+fn lists_component(listing: &str, name: &str) -> bool {
+    listing.lines().any(|line| {
+        line.split_whitespace()
+            .nth(1)
+            .is_some_and(|names| names.split(',').any(|n| n == name))
+    })
+}
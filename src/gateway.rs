@@ -0,0 +1,122 @@
+//! Gateway Event Module
+//!
+//! Bridges serenity's gateway callbacks into the owning BotInstance.
+//! The handler does no work itself; it forwards a condensed event over a channel
+//! so all state changes happen on the instance's command loop.
+
+use crate::player::PlayerButton;
+use serenity::all::{
+    ComponentInteraction, Context, CreateInteractionResponse, EventHandler, Guild, Interaction,
+    PartialGuild, UnavailableGuild, VoiceState,
+};
+use tokio::sync::mpsc::Sender;
+
+/// Gateway events relevant to a BotInstance.
+#[derive(Debug)]
+pub enum GatewayEvent {
+    /// The bot's own voice state changed (joined, moved, or disconnected).
+    BotVoiceStateUpdate {
+        guild_id: u64,
+        old_channel_id: Option<u64>,
+        channel_id: Option<u64>,
+    },
+    /// The bot joined a guild, or a guild became available after connecting.
+    GuildAvailable { guild_id: u64, name: String },
+    /// A guild's details changed.
+    GuildUpdated { guild_id: u64, name: String },
+    /// The bot was removed from a guild.
+    GuildRemoved { guild_id: u64 },
+    /// A button on a player message was pressed. The interaction is already
+    /// acknowledged, so rejections are sent as follow-ups.
+    PlayerButton {
+        button: PlayerButton,
+        interaction: Box<ComponentInteraction>,
+    },
+}
+
+/// Serenity event handler that forwards gateway events to a BotInstance.
+pub struct GatewayHandler {
+    pub event_tx: Sender<GatewayEvent>,
+}
+
+#[async_trait::async_trait]
+impl EventHandler for GatewayHandler {
+    async fn guild_create(&self, _ctx: Context, guild: Guild, _is_new: Option<bool>) {
+        let _ = self
+            .event_tx
+            .send(GatewayEvent::GuildAvailable {
+                guild_id: guild.id.get(),
+                name: guild.name,
+            })
+            .await;
+    }
+
+    async fn guild_update(&self, _ctx: Context, _old: Option<Guild>, new: PartialGuild) {
+        let _ = self
+            .event_tx
+            .send(GatewayEvent::GuildUpdated {
+                guild_id: new.id.get(),
+                name: new.name,
+            })
+            .await;
+    }
+
+    async fn guild_delete(
+        &self,
+        _ctx: Context,
+        incomplete: UnavailableGuild,
+        _full: Option<Guild>,
+    ) {
+        // An unavailable guild is a Discord outage, not a removal.
+        if incomplete.unavailable {
+            return;
+        }
+
+        let _ = self
+            .event_tx
+            .send(GatewayEvent::GuildRemoved {
+                guild_id: incomplete.id.get(),
+            })
+            .await;
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let Interaction::Component(component) = interaction else {
+            return;
+        };
+        let Some(button) = PlayerButton::from_custom_id(&component.data.custom_id) else {
+            return;
+        };
+        // Discord fails interactions not answered within 3 seconds, and the command
+        // loop may be busy resolving a track for longer than that.
+        let _ = component
+            .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+            .await;
+
+        let _ = self
+            .event_tx
+            .send(GatewayEvent::PlayerButton {
+                button,
+                interaction: Box::new(component),
+            })
+            .await;
+    }
+
+    async fn voice_state_update(&self, ctx: Context, old: Option<VoiceState>, new: VoiceState) {
+        if new.user_id != ctx.cache.current_user().id {
+            return;
+        }
+        let Some(guild_id) = new.guild_id else {
+            return;
+        };
+
+        let _ = self
+            .event_tx
+            .send(GatewayEvent::BotVoiceStateUpdate {
+                guild_id: guild_id.get(),
+                old_channel_id: old.and_then(|o| o.channel_id).map(|c| c.get()),
+                channel_id: new.channel_id.map(|c| c.get()),
+            })
+            .await;
+    }
+}
@@ -3,14 +3,53 @@
 //! - High-contrast dark theme
 //! - Panels style UI
 
+use crate::audio_cache::AudioCache;
 use crate::bot::ManagerCommand;
 use crate::config::ConfigManager;
-use crate::state::{AccountState, AppState, BotCommand, BotStatus, GuildState, SharedState};
+use crate::invite::{INVITE_FEATURES, InviteBuilder};
+use crate::logging::{LogEntry, LogFilter, LogLevel};
+use crate::playlist::{self, ExportFormat, TrackList};
+use crate::scheduler::{JobAction, ONCE_FORMAT, Schedule, ScheduledJob};
+use crate::state::{
+    AccountState, AppState, AudioCacheSettings, BotCommand, BotStatus, DisconnectAction,
+    GuildSettings, GuildState, NameId, SharedState, SleepAfter, SoundClip, SpeechEngine,
+    SpeechSettings, ToolSettings, VoiceChannel, VoiceChannelKind,
+};
+use chrono::Local;
 use eframe::egui;
 use egui::{Color32, FontFamily, FontId, Key, RichText, Stroke, TextStyle};
 use egui_extras::{Column, TableBuilder};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 
+/// Keys offered as soundboard hotkeys. They only fire while no text field has focus.
+const SOUND_HOTKEYS: [Key; 22] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
 /// Main application state struct for the GUI.
 pub struct MusicApp {
     state: SharedState,
@@ -19,6 +58,14 @@ pub struct MusicApp {
     add_account_alias: String,
     show_add_modal: bool,
     url_input: String,
+    invite_builder: Option<InviteBuilder>,
+    log_filter: LogFilter,
+    show_diagnostics: bool,
+    show_schedule: bool,
+    /// The job being added in the schedule window, if any.
+    job_draft: Option<ScheduledJob>,
+    sound_name_input: String,
+    sound_path_input: String,
 }
 
 impl MusicApp {
@@ -37,6 +84,13 @@ impl MusicApp {
             add_account_alias: String::new(),
             show_add_modal: false,
             url_input: String::new(),
+            invite_builder: None,
+            log_filter: LogFilter::default(),
+            show_diagnostics: false,
+            show_schedule: false,
+            job_draft: None,
+            sound_name_input: String::new(),
+            sound_path_input: String::new(),
         }
     }
 
@@ -78,12 +132,28 @@ impl eframe::App for MusicApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
 
-        Self::render_logs_panel(ctx, &state);
+        Self::render_logs_panel(ctx, &state, &mut self.log_filter);
 
-        Self::render_accounts_panel(ctx, &mut state, &self.manager_tx, &mut self.show_add_modal);
+        Self::render_accounts_panel(
+            ctx,
+            &mut state,
+            &self.manager_tx,
+            &mut self.show_add_modal,
+            &mut self.invite_builder,
+            &mut self.show_diagnostics,
+            &mut self.show_schedule,
+        );
 
         Self::render_guilds_panel(ctx, &mut state, &self.manager_tx);
 
+        Self::handle_sound_hotkeys(ctx, &state);
+        Self::render_soundboard_panel(
+            ctx,
+            &mut state,
+            &mut self.sound_name_input,
+            &mut self.sound_path_input,
+        );
+
         Self::render_dashboard(ctx, &mut state, &mut self.url_input);
 
         if self.show_add_modal {
@@ -97,13 +167,39 @@ impl eframe::App for MusicApp {
             );
         }
 
+        if let Some(builder) = &mut self.invite_builder {
+            let mut open = true;
+            Self::render_invite_modal(ctx, builder, &mut open);
+            if !open {
+                self.invite_builder = None;
+            }
+        }
+
+        if self.show_schedule {
+            Self::render_schedule_window(
+                ctx,
+                &mut state,
+                &mut self.job_draft,
+                &mut self.show_schedule,
+            );
+        }
+
+        if self.show_diagnostics {
+            Self::render_diagnostics_modal(
+                ctx,
+                &mut state,
+                &self.manager_tx,
+                &mut self.show_diagnostics,
+            );
+        }
+
         ctx.request_repaint();
     }
 }
 
 impl MusicApp {
-    /// Renders the bottom panel containing system logs.
-    fn render_logs_panel(ctx: &egui::Context, state: &AppState) {
+    /// Renders the bottom panel containing system logs, with level/account/guild filters and search.
+    fn render_logs_panel(ctx: &egui::Context, state: &AppState, filter: &mut LogFilter) {
         egui::TopBottomPanel::bottom("log_panel")
             .resizable(true)
             .min_height(100.0)
@@ -113,16 +209,58 @@ impl MusicApp {
                     .fill(Color32::TRANSPARENT)
                     .inner_margin(8.0)
                     .show(ui, |ui| {
-                        ui.heading("System Logs");
+                        ui.horizontal(|ui| {
+                            ui.heading("System Logs");
+                            ui.separator();
+                            Self::render_log_filters(ui, state, filter);
+                        });
                         ui.separator();
 
+                        let account_alias = |uuid: &str| {
+                            state
+                                .accounts
+                                .get(uuid)
+                                .map(|a| a.alias.clone())
+                                .unwrap_or_else(|| uuid.to_string())
+                        };
+                        let guild_name = |id: u64| {
+                            state
+                                .accounts
+                                .values()
+                                .find_map(|a| a.guilds.get(&id))
+                                .map(|g| g.guild_name.clone())
+                                .unwrap_or_else(|| id.to_string())
+                        };
+
                         egui::ScrollArea::both()
                             .stick_to_bottom(true)
                             .auto_shrink([false, false])
                             .show(ui, |ui| {
-                                for log in &state.system_logs {
+                                for entry in state.system_logs.iter().filter(|e| filter.matches(e))
+                                {
+                                    let level_color = match entry.level {
+                                        LogLevel::Info => Color32::LIGHT_GRAY,
+                                        LogLevel::Warn => Color32::YELLOW,
+                                        LogLevel::Error => Color32::RED,
+                                    };
+
+                                    let mut line = format!(
+                                        "[{}] {:<5} ",
+                                        entry.time().format("%H:%M:%S"),
+                                        format!("{:?}", entry.level).to_uppercase()
+                                    );
+                                    if let Some(uuid) = &entry.account_uuid {
+                                        line.push_str(&format!("[{}] ", account_alias(uuid)));
+                                    }
+                                    if let Some(id) = entry.guild_id {
+                                        line.push_str(&format!("[{}] ", guild_name(id)));
+                                    }
+                                    line.push_str(&entry.message);
+
                                     ui.add(egui::Label::new(
-                                        RichText::new(log).font(FontId::monospace(12.0)),
+                                        RichText::new(line)
+                                            .font(FontId::monospace(12.0))
+                                            .color(level_color),
                                     ));
                                 }
                             });
@@ -130,12 +268,77 @@ impl MusicApp {
             });
     }
 
+    /// Renders the filter controls shown in the log panel header.
+    fn render_log_filters(ui: &mut egui::Ui, state: &AppState, filter: &mut LogFilter) {
+        egui::ComboBox::from_id_salt("log_level")
+            .selected_text(format!("{:?}+", filter.min_level))
+            .show_ui(ui, |ui| {
+                for level in [LogLevel::Info, LogLevel::Warn, LogLevel::Error] {
+                    ui.selectable_value(&mut filter.min_level, level, format!("{:?}+", level));
+                }
+            });
+
+        let mut accounts: Vec<_> = state.accounts.values().collect();
+        accounts.sort_by(|a, b| a.alias.cmp(&b.alias));
+        let account_text = filter
+            .account_uuid
+            .as_ref()
+            .and_then(|uuid| state.accounts.get(uuid))
+            .map(|a| a.alias.clone())
+            .unwrap_or_else(|| "All Accounts".to_string());
+        egui::ComboBox::from_id_salt("log_account")
+            .selected_text(account_text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.account_uuid, None, "All Accounts");
+                for acc in &accounts {
+                    ui.selectable_value(
+                        &mut filter.account_uuid,
+                        Some(acc.uuid.clone()),
+                        &acc.alias,
+                    );
+                }
+            });
+
+        let mut guilds: Vec<_> = accounts
+            .iter()
+            .filter(|a| {
+                filter.account_uuid.is_none() || filter.account_uuid.as_ref() == Some(&a.uuid)
+            })
+            .flat_map(|a| a.guilds.values())
+            .map(|g| (g.guild_id, g.guild_name.clone()))
+            .collect();
+        guilds.sort_by(|a, b| a.1.cmp(&b.1));
+        guilds.dedup_by_key(|g| g.0);
+        let guild_text = filter
+            .guild_id
+            .and_then(|id| guilds.iter().find(|g| g.0 == id))
+            .map(|g| g.1.clone())
+            .unwrap_or_else(|| "All Servers".to_string());
+        egui::ComboBox::from_id_salt("log_guild")
+            .selected_text(guild_text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.guild_id, None, "All Servers");
+                for (id, name) in &guilds {
+                    ui.selectable_value(&mut filter.guild_id, Some(*id), name);
+                }
+            });
+
+        ui.add(
+            egui::TextEdit::singleline(&mut filter.search)
+                .desired_width(200.0)
+                .hint_text("Search..."),
+        );
+    }
+
     /// Renders the left-most panel containing the list of configured bot accounts.
     fn render_accounts_panel(
         ctx: &egui::Context,
         state: &mut AppState,
         manager_tx: &Sender<ManagerCommand>,
         show_add_modal: &mut bool,
+        invite_builder: &mut Option<InviteBuilder>,
+        show_diagnostics: &mut bool,
+        show_schedule: &mut bool,
     ) {
         egui::SidePanel::left("accounts_panel")
             .exact_width(220.0)
@@ -160,7 +363,11 @@ impl MusicApp {
 
                                     for account in accounts {
                                         Self::render_account_item(
-                                            ctx, ui, state, &account, manager_tx,
+                                            ui,
+                                            state,
+                                            &account,
+                                            manager_tx,
+                                            invite_builder,
                                         );
                                     }
                                 },
@@ -171,6 +378,21 @@ impl MusicApp {
                             if ui.button(" + Add Account ").clicked() {
                                 *show_add_modal = true;
                             }
+
+                            let healthy =
+                                state.dependency_report.as_ref().is_none_or(|r| r.is_ok());
+                            let label = if healthy {
+                                RichText::new("Diagnostics")
+                            } else {
+                                RichText::new("Diagnostics (!)")
+                                    .color(Color32::from_rgb(220, 180, 50))
+                            };
+                            if ui.button(label).clicked() {
+                                *show_diagnostics = true;
+                            }
+                            if ui.button("Schedule").clicked() {
+                                *show_schedule = true;
+                            }
                             ui.separator();
                         });
                     });
@@ -179,11 +401,11 @@ impl MusicApp {
 
     /// Renders a single account row in the accounts panel.
     fn render_account_item(
-        ctx: &egui::Context,
         ui: &mut egui::Ui,
         state: &mut AppState,
         account: &AccountState,
         manager_tx: &Sender<ManagerCommand>,
+        invite_builder: &mut Option<InviteBuilder>,
     ) {
         let is_selected = state.ui_context.selected_account_uuid.as_deref() == Some(&account.uuid);
 
@@ -214,9 +436,8 @@ impl MusicApp {
             ui.separator();
 
             if let Some(app_id) = account.application_id {
-                if ui.button("Invite to Server").clicked() {
-                    let url = format!("https://discord.com/api/oauth2/authorize?client_id={}&permissions=36700160&scope=bot", app_id);
-                    ctx.open_url(egui::OpenUrl::new_tab(url));
+                if ui.button("Invite to Server...").clicked() {
+                    *invite_builder = Some(InviteBuilder::new(app_id));
                 }
             } else {
                 ui.label(RichText::new("Invite unavailable (No ID)").color(Color32::GRAY));
@@ -326,6 +547,181 @@ impl MusicApp {
             });
     }
 
+    /// The selected guild and its bot's command sender, if the bot is in a voice channel there.
+    fn connected_guild(state: &AppState) -> Option<(Sender<BotCommand>, u64)> {
+        let uuid = state.ui_context.selected_account_uuid.as_ref()?;
+        let gid = state.ui_context.selected_guild_id?;
+        let account = state.accounts.get(uuid)?;
+        account.guilds.get(&gid)?.channel_id?;
+        Some((account.command_tx.clone()?, gid))
+    }
+
+    /// Plays soundboard clips whose hotkey was pressed, unless a text field has focus.
+    fn handle_sound_hotkeys(ctx: &egui::Context, state: &AppState) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let Some((tx, guild_id)) = Self::connected_guild(state) else {
+            return;
+        };
+        for clip in &state.soundboard {
+            if let Some(key) = clip.hotkey.as_deref().and_then(Key::from_name)
+                && ctx.input(|i| i.key_pressed(key))
+            {
+                let _ = tx.try_send(BotCommand::PlaySound {
+                    guild_id,
+                    path: clip.path.clone(),
+                    volume: clip.volume,
+                });
+            }
+        }
+    }
+
+    /// Renders the right panel of soundboard clips, with volume, hotkey and registration controls.
+    fn render_soundboard_panel(
+        ctx: &egui::Context,
+        state: &mut AppState,
+        name: &mut String,
+        path: &mut String,
+    ) {
+        if state.ui_context.selected_guild_id.is_none() {
+            return;
+        }
+
+        let target = Self::connected_guild(state);
+        let mut changed = false;
+        let mut removed = None;
+
+        egui::SidePanel::right("soundboard_panel")
+            .default_width(220.0)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::Frame::default()
+                    .fill(Color32::TRANSPARENT)
+                    .inner_margin(10.0)
+                    .show(ui, |ui| {
+                        ui.heading("SOUNDBOARD");
+                        ui.add_space(5.0);
+                        ui.separator();
+                        ui.add_space(10.0);
+
+                        if target.is_none() {
+                            ui.label(
+                                RichText::new("Join a voice channel to play sounds.")
+                                    .color(Color32::GRAY),
+                            );
+                            ui.add_space(5.0);
+                        }
+
+                        egui::ScrollArea::vertical()
+                            .max_height(ui.available_height() - 120.0)
+                            .show(ui, |ui| {
+                                for (i, clip) in state.soundboard.iter_mut().enumerate() {
+                                    ui.push_id(&clip.uuid, |ui| {
+                                        ui.horizontal(|ui| {
+                                            let label = match &clip.hotkey {
+                                                Some(key) => format!("{} [{}]", clip.name, key),
+                                                None => clip.name.clone(),
+                                            };
+                                            let button = ui
+                                                .add_enabled(
+                                                    target.is_some(),
+                                                    egui::Button::new(label),
+                                                )
+                                                .on_hover_text(&clip.path);
+                                            if button.clicked()
+                                                && let Some((tx, guild_id)) = &target
+                                            {
+                                                let _ = tx.try_send(BotCommand::PlaySound {
+                                                    guild_id: *guild_id,
+                                                    path: clip.path.clone(),
+                                                    volume: clip.volume,
+                                                });
+                                            }
+                                            if ui.small_button("Del").clicked() {
+                                                removed = Some(i);
+                                            }
+                                        });
+                                        ui.horizontal(|ui| {
+                                            ui.label("Vol");
+                                            changed |= ui
+                                                .add(
+                                                    egui::Slider::new(&mut clip.volume, 0.0..=1.0)
+                                                        .show_value(false),
+                                                )
+                                                .drag_stopped();
+                                            changed |=
+                                                Self::render_hotkey_picker(ui, &mut clip.hotkey);
+                                        });
+                                        ui.add_space(6.0);
+                                    });
+                                }
+                            });
+
+                        ui.separator();
+                        ui.add(
+                            egui::TextEdit::singleline(name)
+                                .desired_width(f32::INFINITY)
+                                .hint_text("Name"),
+                        );
+                        ui.add(
+                            egui::TextEdit::singleline(path)
+                                .desired_width(f32::INFINITY)
+                                .hint_text("Path to an audio file"),
+                        );
+                        if ui
+                            .add_enabled(!path.trim().is_empty(), egui::Button::new("Add Sound"))
+                            .clicked()
+                        {
+                            let file = path.trim().to_string();
+                            let title = match name.trim() {
+                                "" => std::path::Path::new(&file)
+                                    .file_stem()
+                                    .map(|stem| stem.to_string_lossy().into_owned())
+                                    .unwrap_or_else(|| file.clone()),
+                                title => title.to_string(),
+                            };
+                            state.soundboard.push(SoundClip {
+                                uuid: uuid::Uuid::new_v4().to_string(),
+                                name: title,
+                                path: file,
+                                volume: 1.0,
+                                hotkey: None,
+                            });
+                            name.clear();
+                            path.clear();
+                            changed = true;
+                        }
+                    });
+            });
+
+        if let Some(i) = removed {
+            state.soundboard.remove(i);
+            changed = true;
+        }
+        if changed {
+            let cfg = ConfigManager::update_from_state(state);
+            let _ = ConfigManager::save(&cfg);
+        }
+    }
+
+    /// Renders a dropdown choosing a clip's hotkey. Returns true if it changed.
+    fn render_hotkey_picker(ui: &mut egui::Ui, hotkey: &mut Option<String>) -> bool {
+        let mut changed = false;
+        egui::ComboBox::from_id_salt("hotkey")
+            .width(70.0)
+            .selected_text(hotkey.as_deref().unwrap_or("No key"))
+            .show_ui(ui, |ui| {
+                changed |= ui.selectable_value(hotkey, None, "No key").changed();
+                for key in SOUND_HOTKEYS {
+                    changed |= ui
+                        .selectable_value(hotkey, Some(key.name().to_string()), key.name())
+                        .changed();
+                }
+            });
+        changed
+    }
+
     /// Renders the main dashboard area with player controls and the track queue.
     fn render_dashboard(ctx: &egui::Context, state: &mut AppState, url_input: &mut String) {
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -342,13 +738,18 @@ impl MusicApp {
 
                         if let Some(account) = state.accounts.get_mut(&uuid) {
                             if let Some(guild) = account.guilds.get_mut(&gid) {
+                                let mut export_result = None;
                                 Self::render_header(ui, &cmd_tx_opt, guild);
+                                let settings = account.guild_settings.entry(gid).or_default();
+                                let settings_changed =
+                                    Self::render_guild_settings(ui, settings, &guild.text_channels);
                                 ui.add_space(15.0);
 
                                 if guild.channel_id.is_some() {
                                     Self::render_player_box(ui, &cmd_tx_opt, guild, url_input);
                                     ui.add_space(15.0);
-                                    Self::render_queue_table(ui, &cmd_tx_opt, guild);
+                                    export_result =
+                                        Self::render_queue_table(ui, &cmd_tx_opt, guild);
                                 } else {
                                     ui.centered_and_justified(|ui| {
                                         ui.label(
@@ -359,6 +760,28 @@ impl MusicApp {
                                         );
                                     });
                                 }
+
+                                if settings_changed {
+                                    let cfg = ConfigManager::update_from_state(state);
+                                    let _ = ConfigManager::save(&cfg);
+                                }
+                                if let Some(result) = export_result {
+                                    let (level, message) = match result {
+                                        Ok(path) => (
+                                            LogLevel::Info,
+                                            format!("Exported tracks to {}", path.display()),
+                                        ),
+                                        Err(e) => {
+                                            (LogLevel::Error, format!("Export failed: {:#}", e))
+                                        }
+                                    };
+                                    state.log_entry(LogEntry::new(
+                                        level,
+                                        Some(uuid),
+                                        Some(gid),
+                                        message,
+                                    ));
+                                }
                                 return;
                             }
                         }
@@ -396,10 +819,26 @@ impl MusicApp {
 
             egui::ComboBox::from_id_salt("chan_sel")
                 .selected_text(current)
+                .width(260.0)
                 .show_ui(ui, |ui| {
+                    let mut last_category = None;
                     for c in &guild.voice_channels {
+                        let category = c.category.as_ref().map(|cat| cat.id);
+                        if category != last_category {
+                            if let Some(cat) = &c.category {
+                                ui.label(RichText::new(cat.name.to_uppercase()).small().weak());
+                            }
+                            last_category = category;
+                        }
+
+                        let problem = Self::channel_problem(c);
+                        let item = egui::Button::selectable(
+                            Some(c.id) == guild.channel_id,
+                            Self::channel_label(c),
+                        );
                         if ui
-                            .selectable_label(Some(c.id) == guild.channel_id, &c.name)
+                            .add_enabled(problem.is_none(), item)
+                            .on_disabled_hover_text(problem.unwrap_or_default())
                             .clicked()
                         {
                             if let Some(t) = tx {
@@ -422,6 +861,175 @@ impl MusicApp {
                 }
             }
         });
+
+        if let Some(err) = &guild.join_error {
+            ui.label(RichText::new(format!("Cannot join: {}", err)).color(Color32::RED));
+        }
+    }
+
+    /// Returns why the bot cannot use a channel, if it cannot.
+    fn channel_problem(channel: &VoiceChannel) -> Option<&'static str> {
+        if !channel.can_connect {
+            Some("Missing Connect permission")
+        } else if !channel.can_speak {
+            Some("Missing Speak permission")
+        } else if channel.is_full {
+            Some("Channel is full")
+        } else {
+            None
+        }
+    }
+
+    /// Formats a channel picker entry with its kind and occupancy, e.g. `[Stage] Events (3/10)`.
+    fn channel_label(channel: &VoiceChannel) -> String {
+        let prefix = match channel.kind {
+            VoiceChannelKind::Voice => "",
+            VoiceChannelKind::Stage => "[Stage] ",
+        };
+        let occupancy = match channel.user_limit {
+            Some(limit) => format!("{}/{}", channel.member_count, limit),
+            None => channel.member_count.to_string(),
+        };
+        format!("{}{} ({})", prefix, channel.name, occupancy)
+    }
+
+    /// Renders the collapsible per-guild settings. Returns true if any setting changed.
+    fn render_guild_settings(
+        ui: &mut egui::Ui,
+        settings: &mut GuildSettings,
+        text_channels: &[NameId],
+    ) -> bool {
+        let mut changed = false;
+        egui::CollapsingHeader::new("Settings")
+            .id_salt("guild_settings")
+            .show(ui, |ui| {
+                changed |= Self::render_timeout_setting(
+                    ui,
+                    "Leave when queue is empty for",
+                    &mut settings.idle_timeout_mins,
+                );
+                changed |= Self::render_timeout_setting(
+                    ui,
+                    "Leave when no one is listening for",
+                    &mut settings.alone_timeout_mins,
+                );
+
+                ui.horizontal(|ui| {
+                    ui.label("When disconnected by others:");
+                    egui::ComboBox::from_id_salt("disconnect_action")
+                        .selected_text(format!("{:?}", settings.on_disconnect))
+                        .show_ui(ui, |ui| {
+                            for action in [DisconnectAction::Pause, DisconnectAction::Clear] {
+                                changed |= ui
+                                    .selectable_value(
+                                        &mut settings.on_disconnect,
+                                        action,
+                                        format!("{:?}", action),
+                                    )
+                                    .changed();
+                            }
+                        });
+                    changed |= ui
+                        .checkbox(&mut settings.auto_rejoin, "Auto-rejoin")
+                        .changed();
+                });
+
+                changed |= Self::render_text_channel_setting(
+                    ui,
+                    "Now-playing announcements:",
+                    &mut settings.announce_channel_id,
+                    text_channels,
+                );
+                changed |= Self::render_text_channel_setting(
+                    ui,
+                    "Player message:",
+                    &mut settings.player_channel_id,
+                    text_channels,
+                );
+                changed |= ui
+                    .checkbox(
+                        &mut settings.speak_next_track,
+                        "Say \"Next up\" in voice between tracks",
+                    )
+                    .changed();
+
+                changed |= ui
+                    .checkbox(
+                        &mut settings.autoplay,
+                        "Autoplay related tracks when the queue runs out",
+                    )
+                    .changed();
+                ui.add_enabled_ui(settings.autoplay, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Fallback playlist:");
+                        let mut fallback = settings.autoplay_fallback.clone().unwrap_or_default();
+                        if ui
+                            .add(
+                                egui::TextEdit::singleline(&mut fallback)
+                                    .hint_text("Path or URL of an M3U/PLS/JSON playlist"),
+                            )
+                            .changed()
+                        {
+                            settings.autoplay_fallback =
+                                (!fallback.trim().is_empty()).then_some(fallback);
+                            changed = true;
+                        }
+                    });
+                });
+            });
+        changed
+    }
+
+    /// Renders an optional text channel as a dropdown with a "Disabled" entry.
+    fn render_text_channel_setting(
+        ui: &mut egui::Ui,
+        label: &str,
+        value: &mut Option<u64>,
+        text_channels: &[NameId],
+    ) -> bool {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            let current = value
+                .and_then(|id| text_channels.iter().find(|c| c.id == id))
+                .map(|c| format!("#{}", c.name))
+                .unwrap_or_else(|| "Disabled".to_string());
+
+            let mut changed = false;
+            egui::ComboBox::from_id_salt(label)
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(value, None, "Disabled").changed();
+                    for c in text_channels {
+                        changed |= ui
+                            .selectable_value(value, Some(c.id), format!("#{}", c.name))
+                            .changed();
+                    }
+                });
+            changed
+        })
+        .inner
+    }
+
+    /// Renders an optional timeout in minutes as a checkbox followed by a value field.
+    fn render_timeout_setting(ui: &mut egui::Ui, label: &str, value: &mut Option<u64>) -> bool {
+        ui.horizontal(|ui| {
+            let mut enabled = value.is_some();
+            let mut mins = value.unwrap_or(5);
+
+            let mut changed = ui.checkbox(&mut enabled, label).changed();
+            changed |= ui
+                .add_enabled(
+                    enabled,
+                    egui::DragValue::new(&mut mins)
+                        .range(0..=240)
+                        .suffix(" min"),
+                )
+                .changed();
+
+            *value = enabled.then_some(mins);
+            changed
+        })
+        .inner
     }
 
     /// Renders the audio player controls.
@@ -521,6 +1129,20 @@ impl MusicApp {
                                 });
                             }
                         }
+
+                        if ui
+                            .add_sized(
+                                [60.0, 20.0],
+                                egui::Button::selectable(guild.is_looping, "Loop"),
+                            )
+                            .clicked()
+                            && let Some(t) = tx
+                        {
+                            let _ = t.try_send(BotCommand::SetLoop {
+                                guild_id: guild.guild_id,
+                                enabled: !guild.is_looping,
+                            });
+                        }
                     });
                 });
 
@@ -531,43 +1153,208 @@ impl MusicApp {
                 ui.horizontal(|ui| {
                     ui.label("Add Track:");
 
-                    let btn_width = 80.0;
+                    let btn_width = 210.0;
                     let input_width = ui.available_width() - btn_width - 10.0;
 
                     let response = ui.add(
                         egui::TextEdit::singleline(url)
                             .desired_width(input_width)
-                            .hint_text("Paste YouTube URL here..."),
+                            .hint_text("Paste a URL, file path or playlist here..."),
                     );
 
                     let clicked_add = ui.add(egui::Button::new("Enqueue")).clicked();
+                    let clicked_import = ui
+                        .add(egui::Button::new("Import"))
+                        .on_hover_text("Enqueue every track in an M3U/M3U8/PLS playlist.")
+                        .clicked();
+
+                    let clicked_say = ui
+                        .add(egui::Button::new("Say"))
+                        .on_hover_text("Speak the text in voice.")
+                        .clicked();
+                    if clicked_say
+                        && !url.trim().is_empty()
+                        && let Some(t) = tx
+                    {
+                        let _ = t.try_send(BotCommand::Say {
+                            guild_id: guild.guild_id,
+                            text: url.trim().to_string(),
+                        });
+                        url.clear();
+                    }
 
                     let enter_pressed =
                         response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
 
-                    if (clicked_add || enter_pressed) && !url.is_empty() {
-                        if let Some(t) = tx {
-                            let _ = t.try_send(BotCommand::Play {
-                                guild_id: guild.guild_id,
+                    if (clicked_add || clicked_import || enter_pressed)
+                        && !url.is_empty()
+                        && let Some(t) = tx
+                    {
+                        let guild_id = guild.guild_id;
+                        let cmd = if clicked_import || playlist::is_playlist(url) {
+                            BotCommand::ImportPlaylist {
+                                guild_id,
+                                source: url.trim().to_string(),
+                                requested_by: "Dashboard".to_string(),
+                            }
+                        } else {
+                            BotCommand::Play {
+                                guild_id,
                                 url: url.clone(),
+                                requested_by: "Dashboard".to_string(),
+                            }
+                        };
+                        let _ = t.try_send(cmd);
+                        url.clear();
+                        response.request_focus();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Sleep timer:");
+                    match &guild.sleep_timer {
+                        Some(timer) => {
+                            let action = if timer.leave {
+                                "Stopping and leaving"
+                            } else {
+                                "Stopping"
+                            };
+                            ui.label(
+                                RichText::new(format!(
+                                    "{} {}",
+                                    action,
+                                    timer.describe(Local::now().timestamp_millis())
+                                ))
+                                .color(Color32::from_rgb(220, 180, 50)),
+                            );
+                            if ui.small_button("Cancel").clicked()
+                                && let Some(t) = tx
+                            {
+                                let _ = t.try_send(BotCommand::CancelSleepTimer {
+                                    guild_id: guild.guild_id,
+                                });
+                            }
+                        }
+                        None => {
+                            ui.menu_button("Set...", |ui| {
+                                let leave_id = ui.make_persistent_id("sleep_leave");
+                                let mut leave = ui.data_mut(|d| *d.get_temp_mut_or(leave_id, true));
+                                if ui.checkbox(&mut leave, "Leave voice too").changed() {
+                                    ui.data_mut(|d| d.insert_temp(leave_id, leave));
+                                }
+                                ui.separator();
+
+                                let choices = [
+                                    (
+                                        "In 15 minutes",
+                                        SleepAfter::Duration(Duration::from_secs(15 * 60)),
+                                    ),
+                                    (
+                                        "In 30 minutes",
+                                        SleepAfter::Duration(Duration::from_secs(30 * 60)),
+                                    ),
+                                    (
+                                        "In 1 hour",
+                                        SleepAfter::Duration(Duration::from_secs(60 * 60)),
+                                    ),
+                                    (
+                                        "In 2 hours",
+                                        SleepAfter::Duration(Duration::from_secs(2 * 60 * 60)),
+                                    ),
+                                    ("After this track", SleepAfter::AfterCurrentTrack),
+                                    ("When the queue ends", SleepAfter::AfterQueue),
+                                ];
+                                for (label, after) in choices {
+                                    if ui.button(label).clicked() {
+                                        if let Some(t) = tx {
+                                            let _ = t.try_send(BotCommand::SetSleepTimer {
+                                                guild_id: guild.guild_id,
+                                                after,
+                                                leave,
+                                            });
+                                        }
+                                        ui.close();
+                                    }
+                                }
                             });
-                            url.clear();
-                            response.request_focus();
                         }
                     }
                 });
+
+                if !guild.pending_imports.is_empty() {
+                    ui.label(
+                        RichText::new(format!(
+                            "Importing playlist: {} tracks left to resolve...",
+                            guild.pending_imports.len()
+                        ))
+                        .weak(),
+                    );
+                }
+
+                if let Some(err) = &guild.source_error {
+                    ui.label(
+                        RichText::new(format!("Last track failed: {}", err)).color(Color32::RED),
+                    );
+                }
             });
     }
 
     /// Renders the track queue table.
-    fn render_queue_table(ui: &mut egui::Ui, tx: &Option<Sender<BotCommand>>, guild: &GuildState) {
+    ///
+    /// Returns the result of an export, if one was requested this frame.
+    fn render_queue_table(
+        ui: &mut egui::Ui,
+        tx: &Option<Sender<BotCommand>>,
+        guild: &GuildState,
+    ) -> Option<anyhow::Result<PathBuf>> {
+        let mut exported = None;
         ui.group(|ui| {
             ui.horizontal(|ui| {
                 ui.heading("Queue");
-                ui.label(RichText::new(format!("({} tracks)", guild.queue.len())).weak());
+                let count = guild.queue.len() + guild.pending_imports.len();
+                ui.label(RichText::new(format!("({} tracks)", count)).weak());
 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if !guild.queue.is_empty() {
+                    ui.menu_button("Export", |ui| {
+                        for list in TrackList::ALL {
+                            let tracks = list.tracks(guild);
+                            for format in ExportFormat::ALL {
+                                let text = format!(
+                                    "{} as .{} ({} tracks)",
+                                    list.label(),
+                                    format.extension(),
+                                    tracks.len()
+                                );
+                                if ui
+                                    .add_enabled(!tracks.is_empty(), egui::Button::new(text))
+                                    .clicked()
+                                {
+                                    // A running bot exports off the UI thread; history is
+                                    // still exportable after the bot has stopped
+                                    match tx {
+                                        Some(t) => {
+                                            let _ = t.try_send(BotCommand::ExportTracks {
+                                                guild_id: guild.guild_id,
+                                                which: list,
+                                                format,
+                                                path: None,
+                                            });
+                                        }
+                                        None => {
+                                            let name =
+                                                format!("{} {}", guild.guild_name, list.label());
+                                            exported = Some(playlist::export(
+                                                &tracks, &name, format, None,
+                                            ));
+                                        }
+                                    }
+                                    ui.close();
+                                }
+                            }
+                        }
+                    });
+
+                    if !guild.queue.is_empty() || !guild.pending_imports.is_empty() {
                         if ui.button("Clear").clicked() {
                             if let Some(t) = tx {
                                 let _ = t.try_send(BotCommand::ClearQueue {
@@ -590,6 +1377,7 @@ impl MusicApp {
                         .column(Column::exact(30.0))
                         .column(Column::remainder())
                         .column(Column::exact(60.0))
+                        .column(Column::exact(70.0))
                         .column(Column::exact(140.0))
                         .header(20.0, |mut header| {
                             header.col(|ui| {
@@ -601,6 +1389,9 @@ impl MusicApp {
                             header.col(|ui| {
                                 ui.label("Time");
                             });
+                            header.col(|ui| {
+                                ui.label("Gain");
+                            });
                             header.col(|ui| {
                                 ui.label("Actions");
                             });
@@ -621,6 +1412,31 @@ impl MusicApp {
                                         let s = track.duration_secs.unwrap_or(0);
                                         ui.label(format!("{:02}:{:02}", s / 60, s % 60));
                                     });
+                                    row.col(|ui| {
+                                        let mut pct = track.gain.unwrap_or(1.0) * 100.0;
+                                        let response = ui
+                                            .add(
+                                                egui::DragValue::new(&mut pct)
+                                                    .range(0.0..=200.0)
+                                                    .speed(1.0)
+                                                    .suffix("%"),
+                                            )
+                                            .on_hover_text(
+                                                "Relative to the guild volume. Double-click to reset.",
+                                            );
+                                        let reset = response.double_clicked();
+                                        if (reset || response.drag_stopped() || response.lost_focus())
+                                            && let Some(t) = tx
+                                        {
+                                            let gain = (!reset && pct.round() != 100.0)
+                                                .then_some(pct / 100.0);
+                                            let _ = t.try_send(BotCommand::SetTrackGain {
+                                                guild_id: guild.guild_id,
+                                                track_uuid: track.uuid.clone(),
+                                                gain,
+                                            });
+                                        }
+                                    });
                                     row.col(|ui| {
                                         ui.horizontal(|ui| {
                                             if ui
@@ -664,9 +1480,621 @@ impl MusicApp {
                                     });
                                 });
                             }
+
+                            // Imported entries are only resolved as the queue runs low
+                            for (i, track) in guild.pending_imports.iter().enumerate() {
+                                body.row(24.0, |mut row| {
+                                    row.col(|ui| {
+                                        ui.label((guild.queue.len() + i + 1).to_string());
+                                    });
+                                    row.col(|ui| {
+                                        ui.add(
+                                            egui::Label::new(RichText::new(&track.title).weak())
+                                                .truncate(),
+                                        );
+                                    });
+                                    row.col(|ui| {
+                                        if let Some(s) = track.duration_secs {
+                                            ui.label(format!("{:02}:{:02}", s / 60, s % 60));
+                                        }
+                                    });
+                                    row.col(|_| {});
+                                    row.col(|ui| {
+                                        ui.label(RichText::new("Pending").weak());
+                                    });
+                                });
+                            }
+                        });
+                });
+        });
+        exported
+    }
+
+    /// Renders the invite link builder for choosing scopes and feature permissions.
+    fn render_invite_modal(ctx: &egui::Context, builder: &mut InviteBuilder, open: &mut bool) {
+        egui::Window::new("Invite to Server")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.set_min_width(360.0);
+
+                ui.label(RichText::new("Scopes").strong());
+                ui.checkbox(&mut builder.bot_scope, "bot");
+                ui.checkbox(&mut builder.commands_scope, "applications.commands");
+                ui.add_space(10.0);
+
+                ui.label(RichText::new("Features").strong());
+                ui.add_enabled_ui(builder.bot_scope, |ui| {
+                    for (feature, enabled) in INVITE_FEATURES.iter().zip(&mut builder.features) {
+                        ui.checkbox(enabled, feature.name)
+                            .on_hover_text(feature.permissions.get_permission_names().join(", "));
+                    }
+                });
+                ui.add_space(10.0);
+
+                let permissions = builder.permissions();
+                ui.label(format!("Permissions integer: {}", permissions.bits()));
+                ui.label(
+                    RichText::new(permissions.get_permission_names().join(", "))
+                        .small()
+                        .weak(),
+                );
+                ui.add_space(10.0);
+
+                let url = builder.url();
+                ui.add(
+                    egui::Label::new(
+                        RichText::new(url.as_deref().unwrap_or("Select at least one scope."))
+                            .font(FontId::monospace(11.0)),
+                    )
+                    .wrap(),
+                );
+                ui.add_space(15.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Close").clicked() {
+                        *open = false;
+                    }
+                    if let Some(url) = url {
+                        if ui.button("Copy Link").clicked() {
+                            ctx.copy_text(url.clone());
+                        }
+                        if ui.button("Open in Browser").clicked() {
+                            ctx.open_url(egui::OpenUrl::new_tab(url));
+                        }
+                    }
+                });
+            });
+    }
+
+    /// Renders the dependency health check report with a button to re-run it.
+    fn render_diagnostics_modal(
+        ctx: &egui::Context,
+        state: &mut AppState,
+        manager_tx: &Sender<ManagerCommand>,
+        open: &mut bool,
+    ) {
+        egui::Window::new("Diagnostics")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.set_min_width(420.0);
+
+                match &state.dependency_report {
+                    Some(report) => {
+                        for status in [&report.yt_dlp, &report.ffmpeg] {
+                            ui.horizontal(|ui| {
+                                let (mark, color) = if status.is_ok() {
+                                    ("OK", Color32::GREEN)
+                                } else {
+                                    ("!!", Color32::from_rgb(220, 80, 80))
+                                };
+                                ui.label(RichText::new(mark).color(color).strong());
+                                ui.label(RichText::new(&status.name).strong());
+                                ui.label(status.version.as_deref().unwrap_or("-"));
+                            });
+                            if let Some(path) = &status.path {
+                                ui.label(RichText::new(path.display().to_string()).small().weak());
+                            }
+                            for problem in &status.problems {
+                                ui.label(
+                                    RichText::new(problem).color(Color32::from_rgb(220, 180, 50)),
+                                );
+                            }
+                            ui.add_space(8.0);
+                        }
+                        ui.label(
+                            RichText::new(format!("Checked at {}", report.checked_at))
+                                .small()
+                                .weak(),
+                        );
+                    }
+                    None => {
+                        ui.label("No check has completed yet.");
+                    }
+                }
+                ui.add_space(10.0);
+
+                egui::CollapsingHeader::new("Tool Settings").show(ui, |ui| {
+                    if Self::render_tool_settings(ui, &mut state.tool_settings) {
+                        let cfg = ConfigManager::update_from_state(state);
+                        let _ = ConfigManager::save(&cfg);
+                    }
+                });
+                egui::CollapsingHeader::new("Audio Cache").show(ui, |ui| {
+                    if Self::render_audio_cache_settings(ui, &mut state.audio_cache_settings) {
+                        let cfg = ConfigManager::update_from_state(state);
+                        let _ = ConfigManager::save(&cfg);
+                    }
+                });
+                egui::CollapsingHeader::new("Speech").show(ui, |ui| {
+                    if Self::render_speech_settings(ui, &mut state.speech_settings) {
+                        let cfg = ConfigManager::update_from_state(state);
+                        let _ = ConfigManager::save(&cfg);
+                    }
+                });
+                ui.add_space(15.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Close").clicked() {
+                        *open = false;
+                    }
+                    let running = state.dependency_check_running;
+                    let label = if running {
+                        "Checking..."
+                    } else {
+                        "Re-run Checks"
+                    };
+                    if ui.add_enabled(!running, egui::Button::new(label)).clicked() {
+                        let _ = manager_tx.try_send(ManagerCommand::CheckDependencies);
+                    }
+
+                    let updating = state.yt_dlp_updating;
+                    let resolving = state.resolving_tracks > 0;
+                    let label = if updating {
+                        "Updating..."
+                    } else {
+                        "Update yt-dlp"
+                    };
+                    let button = ui
+                        .add_enabled(!updating && !resolving, egui::Button::new(label))
+                        .on_disabled_hover_text("Unavailable while tracks are resolving.");
+                    if button.clicked() {
+                        let _ = manager_tx.try_send(ManagerCommand::UpdateYtDlp);
+                    }
+                });
+            });
+    }
+
+    /// Renders the scheduled jobs with toggles and removal, and the form for adding one.
+    fn render_schedule_window(
+        ctx: &egui::Context,
+        state: &mut AppState,
+        draft: &mut Option<ScheduledJob>,
+        open: &mut bool,
+    ) {
+        let mut changed = false;
+        let mut removed = None;
+
+        egui::Window::new("Schedule")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                if state.scheduled_jobs.is_empty() {
+                    ui.label(RichText::new("No scheduled jobs.").color(Color32::GRAY));
+                } else {
+                    egui::Grid::new("scheduled_jobs")
+                        .num_columns(6)
+                        .striped(true)
+                        .spacing([10.0, 6.0])
+                        .show(ui, |ui| {
+                            for (i, job) in state.scheduled_jobs.iter_mut().enumerate() {
+                                changed |= ui.checkbox(&mut job.enabled, "").changed();
+                                ui.label(RichText::new(&job.name).strong());
+                                let when = match &job.schedule {
+                                    Schedule::Once(at) => format!("once at {}", at),
+                                    Schedule::Cron(expr) => format!("cron {}", expr),
+                                };
+                                ui.label(when);
+                                let account = state
+                                    .accounts
+                                    .get(&job.account_uuid)
+                                    .map(|a| a.alias.as_str())
+                                    .unwrap_or("(removed account)");
+                                ui.label(format!("{}: {}", account, job.action.label()));
+                                ui.label(
+                                    RichText::new(match &job.last_run {
+                                        Some(at) => format!("last run {}", at),
+                                        None => "never run".to_string(),
+                                    })
+                                    .weak(),
+                                );
+                                if ui.small_button("Del").clicked() {
+                                    removed = Some(i);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                }
+                ui.add_space(10.0);
+                ui.separator();
+
+                match draft {
+                    Some(job) => {
+                        let (save, cancel) = Self::render_job_form(ui, state, job);
+                        if save {
+                            state.scheduled_jobs.push(job.clone());
+                            changed = true;
+                        }
+                        if save || cancel {
+                            *draft = None;
+                        }
+                    }
+                    None => {
+                        ui.horizontal(|ui| {
+                            let first_account = state.accounts.keys().min().cloned();
+                            if ui
+                                .add_enabled(first_account.is_some(), egui::Button::new("New Job"))
+                                .clicked()
+                                && let Some(uuid) = first_account
+                            {
+                                *draft = Some(ScheduledJob::new(uuid));
+                            }
+                            if ui.button("Close").clicked() {
+                                *open = false;
+                            }
                         });
+                    }
+                }
+            });
+
+        if let Some(i) = removed {
+            state.scheduled_jobs.remove(i);
+            changed = true;
+        }
+        if changed {
+            let cfg = ConfigManager::update_from_state(state);
+            let _ = ConfigManager::save(&cfg);
+        }
+    }
+
+    /// Renders the fields of a new job. Returns whether it was saved or cancelled.
+    fn render_job_form(
+        ui: &mut egui::Ui,
+        state: &AppState,
+        job: &mut ScheduledJob,
+    ) -> (bool, bool) {
+        egui::Grid::new("job_form")
+            .num_columns(2)
+            .spacing([10.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("Name");
+                ui.add(egui::TextEdit::singleline(&mut job.name).desired_width(260.0));
+                ui.end_row();
+
+                ui.label("Account");
+                let alias = |uuid: &str| {
+                    state
+                        .accounts
+                        .get(uuid)
+                        .map(|a| a.alias.clone())
+                        .unwrap_or_default()
+                };
+                egui::ComboBox::from_id_salt("job_account")
+                    .selected_text(alias(&job.account_uuid))
+                    .show_ui(ui, |ui| {
+                        let mut accounts: Vec<_> = state.accounts.values().collect();
+                        accounts.sort_by(|a, b| a.alias.cmp(&b.alias));
+                        for account in accounts {
+                            ui.selectable_value(
+                                &mut job.account_uuid,
+                                account.uuid.clone(),
+                                &account.alias,
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("When");
+                ui.horizontal(|ui| {
+                    let is_cron = matches!(job.schedule, Schedule::Cron(_));
+                    if ui.radio(!is_cron, "Once").clicked() && is_cron {
+                        job.schedule = Schedule::Once(
+                            (Local::now() + chrono::Duration::hours(1))
+                                .format(ONCE_FORMAT)
+                                .to_string(),
+                        );
+                    }
+                    if ui.radio(is_cron, "Repeat (cron)").clicked() && !is_cron {
+                        job.schedule = Schedule::Cron("0 20 * * 5".to_string());
+                    }
+                    let (text, hint) = match &mut job.schedule {
+                        Schedule::Once(at) => (at, "2025-01-31 20:00"),
+                        Schedule::Cron(expr) => (expr, "min hour day month weekday"),
+                    };
+                    ui.add(
+                        egui::TextEdit::singleline(text)
+                            .desired_width(160.0)
+                            .hint_text(hint),
+                    );
                 });
+                ui.end_row();
+
+                ui.label("Action");
+                egui::ComboBox::from_id_salt("job_action")
+                    .selected_text(job.action.label())
+                    .show_ui(ui, |ui| {
+                        let guild_id = match &job.action {
+                            JobAction::JoinAndPlay { guild_id, .. }
+                            | JobAction::StopAndLeave { guild_id } => *guild_id,
+                            _ => 0,
+                        };
+                        let choices = [
+                            JobAction::StartBot,
+                            JobAction::StopBot,
+                            JobAction::JoinAndPlay {
+                                guild_id,
+                                channel_id: 0,
+                                source: String::new(),
+                            },
+                            JobAction::StopAndLeave { guild_id },
+                        ];
+                        for choice in choices {
+                            let selected = std::mem::discriminant(&job.action)
+                                == std::mem::discriminant(&choice);
+                            let label = choice.label();
+                            if ui.selectable_label(selected, label).clicked() && !selected {
+                                job.action = choice;
+                            }
+                        }
+                    });
+                ui.end_row();
+
+                let guilds = state.accounts.get(&job.account_uuid).map(|a| &a.guilds);
+                match &mut job.action {
+                    JobAction::JoinAndPlay {
+                        guild_id,
+                        channel_id,
+                        source,
+                    } => {
+                        ui.label("Server");
+                        Self::render_job_guild_picker(ui, guilds, guild_id);
+                        ui.end_row();
+
+                        ui.label("Channel");
+                        let channels = guilds
+                            .and_then(|g| g.get(guild_id))
+                            .map(|g| g.voice_channels.as_slice())
+                            .unwrap_or_default();
+                        let current = channels
+                            .iter()
+                            .find(|c| c.id == *channel_id)
+                            .map(|c| c.name.clone())
+                            .unwrap_or_else(|| "Select...".to_string());
+                        egui::ComboBox::from_id_salt("job_channel")
+                            .selected_text(current)
+                            .show_ui(ui, |ui| {
+                                for c in channels {
+                                    ui.selectable_value(channel_id, c.id, &c.name);
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Play");
+                        ui.add(
+                            egui::TextEdit::singleline(source)
+                                .desired_width(260.0)
+                                .hint_text("URL, file or playlist (optional)"),
+                        );
+                        ui.end_row();
+                    }
+                    JobAction::StopAndLeave { guild_id } => {
+                        ui.label("Server");
+                        Self::render_job_guild_picker(ui, guilds, guild_id);
+                        ui.end_row();
+                    }
+                    JobAction::StartBot | JobAction::StopBot => {}
+                }
+            });
+
+        let error = if job.name.trim().is_empty() {
+            Some("Give the job a name.".to_string())
+        } else if let Err(e) = job.schedule.validate() {
+            Some(e)
+        } else if job.schedule.has_passed(Local::now()) {
+            Some("That time has already passed.".to_string())
+        } else {
+            match &job.action {
+                JobAction::JoinAndPlay { channel_id: 0, .. } => {
+                    Some("Choose a server and voice channel. Select the server in the sidebar to load its channels.".to_string())
+                }
+                JobAction::StopAndLeave { guild_id: 0 } => Some("Choose a server.".to_string()),
+                _ => None,
+            }
+        };
+        if let Some(error) = &error {
+            ui.label(RichText::new(error).color(Color32::from_rgb(220, 180, 50)));
+        }
+
+        ui.horizontal(|ui| {
+            let save = ui
+                .add_enabled(error.is_none(), egui::Button::new("Add Job"))
+                .clicked();
+            let cancel = ui.button("Cancel").clicked();
+            (save, cancel)
+        })
+        .inner
+    }
+
+    /// Renders a dropdown of an account's servers for a job.
+    fn render_job_guild_picker(
+        ui: &mut egui::Ui,
+        guilds: Option<&HashMap<u64, GuildState>>,
+        guild_id: &mut u64,
+    ) {
+        let mut sorted: Vec<&GuildState> = guilds.map(|g| g.values().collect()).unwrap_or_default();
+        sorted.sort_by(|a, b| a.guild_name.cmp(&b.guild_name));
+        let current = sorted
+            .iter()
+            .find(|g| g.guild_id == *guild_id)
+            .map(|g| g.guild_name.clone())
+            .unwrap_or_else(|| "Select...".to_string());
+        egui::ComboBox::from_id_salt("job_guild")
+            .selected_text(current)
+            .show_ui(ui, |ui| {
+                for g in sorted {
+                    ui.selectable_value(guild_id, g.guild_id, &g.guild_name);
+                }
+            });
+    }
+
+    /// Renders the binary path and yt-dlp option fields. Returns true once an edit is committed.
+    fn render_tool_settings(ui: &mut egui::Ui, tools: &mut ToolSettings) -> bool {
+        let mut changed = false;
+        egui::Grid::new("tool_settings")
+            .num_columns(2)
+            .spacing([10.0, 6.0])
+            .show(ui, |ui| {
+                changed |= Self::render_optional_text(ui, "yt-dlp path", &mut tools.yt_dlp_path);
+                changed |= Self::render_optional_text(ui, "ffmpeg path", &mut tools.ffmpeg_path);
+                changed |= Self::render_optional_text(ui, "Cookies file", &mut tools.cookies_file);
+                changed |= Self::render_optional_text(ui, "Proxy", &mut tools.proxy);
+
+                ui.label("Format");
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut tools.format).desired_width(240.0))
+                    .lost_focus();
+                ui.end_row();
+
+                ui.label("Extra args");
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut tools.extra_args)
+                            .desired_width(240.0)
+                            .hint_text("--extractor-args ..."),
+                    )
+                    .lost_focus();
+                ui.end_row();
+
+                ui.label("Auto-update");
+                let mut enabled = tools.auto_update_hours.is_some();
+                let mut hours = tools.auto_update_hours.unwrap_or(24);
+                ui.horizontal(|ui| {
+                    changed |= ui.checkbox(&mut enabled, "every").changed();
+                    changed |= ui
+                        .add_enabled(
+                            enabled,
+                            egui::DragValue::new(&mut hours).range(1..=720).suffix(" h"),
+                        )
+                        .changed();
+                });
+                tools.auto_update_hours = enabled.then_some(hours);
+                ui.end_row();
+            });
+        ui.label(
+            RichText::new("Leave paths empty to search bin/ and PATH.")
+                .small()
+                .weak(),
+        );
+        changed
+    }
+
+    /// Renders the offline audio cache toggle, size cap and usage. Returns true if a setting changed.
+    fn render_audio_cache_settings(ui: &mut egui::Ui, settings: &mut AudioCacheSettings) -> bool {
+        let mut changed = ui
+            .checkbox(
+                &mut settings.enabled,
+                "Keep offline copies of played tracks",
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label("Size limit");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut settings.max_size_mb)
+                        .range(64..=102_400)
+                        .speed(16)
+                        .suffix(" MB"),
+                )
+                .changed();
         });
+
+        let cache = AudioCache::shared();
+        let (tracks, bytes) = cache.usage();
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} tracks, {:.1} MB",
+                tracks,
+                bytes as f64 / (1024.0 * 1024.0)
+            ));
+            if ui
+                .add_enabled(tracks > 0, egui::Button::new("Clear"))
+                .clicked()
+            {
+                cache.clear();
+            }
+        });
+        changed
+    }
+
+    /// Renders the text-to-speech engine, executable and voice. Returns true once an edit is committed.
+    fn render_speech_settings(ui: &mut egui::Ui, settings: &mut SpeechSettings) -> bool {
+        let mut changed = false;
+        egui::Grid::new("speech_settings")
+            .num_columns(2)
+            .spacing([10.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("Engine");
+                egui::ComboBox::from_id_salt("speech_engine")
+                    .selected_text(settings.engine.binary_name())
+                    .show_ui(ui, |ui| {
+                        for engine in [SpeechEngine::EspeakNg, SpeechEngine::Piper] {
+                            changed |= ui
+                                .selectable_value(
+                                    &mut settings.engine,
+                                    engine,
+                                    engine.binary_name(),
+                                )
+                                .changed();
+                        }
+                    });
+                ui.end_row();
+
+                changed |= Self::render_optional_text(ui, "Executable", &mut settings.binary_path);
+
+                ui.label("Voice");
+                let hint = match settings.engine {
+                    SpeechEngine::EspeakNg => "default, or e.g. en-us",
+                    SpeechEngine::Piper => "path/to/voice.onnx",
+                };
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut settings.voice)
+                            .desired_width(240.0)
+                            .hint_text(hint),
+                    )
+                    .lost_focus();
+                ui.end_row();
+            });
+        changed
+    }
+
+    /// Renders a grid row editing an optional string, where empty text means `None`.
+    fn render_optional_text(ui: &mut egui::Ui, label: &str, value: &mut Option<String>) -> bool {
+        ui.label(label);
+        let mut text = value.clone().unwrap_or_default();
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut text)
+                .desired_width(240.0)
+                .hint_text("default"),
+        );
+        ui.end_row();
+
+        *value = (!text.is_empty()).then_some(text);
+        response.lost_focus()
     }
 
     /// Renders the modal dialog for adding a new bot account.
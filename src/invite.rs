@@ -0,0 +1,101 @@
+//! Invite Link Module
+//!
+//! Builds OAuth2 invite URLs for a bot application.
+//! Permissions are derived from the bot features the user wants to enable,
+//! so the invite only requests what those features actually need.
+
+use serenity::all::Permissions;
+
+/// A bot feature and the permissions it needs in a server.
+pub struct InviteFeature {
+    pub name: &'static str,
+    pub permissions: Permissions,
+}
+
+/// Features the invite builder can request permissions for.
+pub const INVITE_FEATURES: &[InviteFeature] = &[
+    InviteFeature {
+        name: "Voice playback",
+        permissions: Permissions::VIEW_CHANNEL
+            .union(Permissions::CONNECT)
+            .union(Permissions::SPEAK)
+            .union(Permissions::USE_VAD),
+    },
+    InviteFeature {
+        name: "Stage channels",
+        permissions: Permissions::REQUEST_TO_SPEAK,
+    },
+    InviteFeature {
+        name: "Text messages",
+        permissions: Permissions::VIEW_CHANNEL
+            .union(Permissions::SEND_MESSAGES)
+            .union(Permissions::EMBED_LINKS),
+    },
+];
+
+/// Editable invite settings for a single application.
+#[derive(Debug, Clone)]
+pub struct InviteBuilder {
+    pub application_id: u64,
+    pub bot_scope: bool,
+    pub commands_scope: bool,
+    /// One flag per entry in [`INVITE_FEATURES`].
+    pub features: Vec<bool>,
+}
+
+impl InviteBuilder {
+    /// Creates a builder with the bot scope and voice playback enabled.
+    pub fn new(application_id: u64) -> Self {
+        let features = INVITE_FEATURES
+            .iter()
+            .map(|f| f.name == "Voice playback")
+            .collect();
+        Self {
+            application_id,
+            bot_scope: true,
+            commands_scope: false,
+            features,
+        }
+    }
+
+    /// The union of the permissions required by the enabled features.
+    pub fn permissions(&self) -> Permissions {
+        INVITE_FEATURES
+            .iter()
+            .zip(&self.features)
+            .filter(|(_, enabled)| **enabled)
+            .fold(Permissions::empty(), |acc, (f, _)| acc | f.permissions)
+    }
+
+    /// The OAuth2 scopes to request, in URL order.
+    pub fn scopes(&self) -> Vec<&'static str> {
+        let mut scopes = Vec::new();
+        if self.bot_scope {
+            scopes.push("bot");
+        }
+        if self.commands_scope {
+            scopes.push("applications.commands");
+        }
+        scopes
+    }
+
+    /// Builds the invite URL, or `None` if no scope is selected.
+    ///
+    /// Permissions are only meaningful for the bot scope, so they are omitted otherwise.
+    pub fn url(&self) -> Option<String> {
+        let scopes = self.scopes();
+        if scopes.is_empty() {
+            return None;
+        }
+
+        let mut url = format!(
+            "https://discord.com/oauth2/authorize?client_id={}",
+            self.application_id
+        );
+        if self.bot_scope {
+            url.push_str(&format!("&permissions={}", self.permissions().bits()));
+        }
+        url.push_str(&format!("&scope={}", scopes.join("%20")));
+        Some(url)
+    }
+}
@@ -0,0 +1,274 @@
+//! Logging Module
+//!
+//! Structured log entries shared by the GUI and bot instances.
+//! Entries are kept in memory for the log panel and appended as JSON lines to
+//! rotating files in a `logs` directory beside the executable, so history
+//! survives restarts. File writes happen on a background thread so logging never
+//! holds the shared state lock across disk I/O.
+
+use crate::config::ConfigManager;
+use crate::state::SharedState;
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+/// Rotate the active log file once it grows past this size.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+/// Number of rotated files kept alongside the active one.
+const ROTATED_FILES: usize = 4;
+
+/// Severity of a log entry.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+/// A single structured log entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Unix timestamp in milliseconds.
+    pub timestamp_ms: i64,
+    pub level: LogLevel,
+    /// The bot account that produced the entry, if any.
+    pub account_uuid: Option<String>,
+    /// The guild the entry relates to, if any.
+    pub guild_id: Option<u64>,
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Creates an entry stamped with the current time.
+    pub fn new(
+        level: LogLevel,
+        account_uuid: Option<String>,
+        guild_id: Option<u64>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            timestamp_ms: Local::now().timestamp_millis(),
+            level,
+            account_uuid,
+            guild_id,
+            message: message.into(),
+        }
+    }
+
+    /// The entry's timestamp in local time.
+    pub fn time(&self) -> DateTime<Local> {
+        Local
+            .timestamp_millis_opt(self.timestamp_ms)
+            .single()
+            .unwrap_or_else(Local::now)
+    }
+}
+
+/// Criteria for showing entries in the log panel.
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    pub min_level: LogLevel,
+    pub account_uuid: Option<String>,
+    pub guild_id: Option<u64>,
+    pub search: String,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            min_level: LogLevel::Info,
+            account_uuid: None,
+            guild_id: None,
+            search: String::new(),
+        }
+    }
+}
+
+impl LogFilter {
+    /// Whether an entry passes every active criterion. Search is case-insensitive.
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        entry.level >= self.min_level
+            && (self.account_uuid.is_none() || entry.account_uuid == self.account_uuid)
+            && (self.guild_id.is_none() || entry.guild_id == self.guild_id)
+            && (self.search.is_empty()
+                || entry
+                    .message
+                    .to_lowercase()
+                    .contains(&self.search.to_lowercase()))
+    }
+}
+
+/// Path of the active log file, or of a rotated one when `index > 0`.
+fn log_path(index: usize) -> PathBuf {
+    let name = if index == 0 {
+        "app.log".to_string()
+    } else {
+        format!("app.{}.log", index)
+    };
+    ConfigManager::data_dir().join("logs").join(name)
+}
+
+/// Shifts `app.log` to `app.1.log`, `app.1.log` to `app.2.log`, and so on, dropping the oldest.
+fn rotate() -> io::Result<()> {
+    let _ = fs::remove_file(log_path(ROTATED_FILES));
+    for index in (0..ROTATED_FILES).rev() {
+        let from = log_path(index);
+        if from.exists() {
+            fs::rename(from, log_path(index + 1))?;
+        }
+    }
+    Ok(())
+}
+
+/// The background writer's queue, set once by [`start_writer`].
+static WRITER: OnceLock<Sender<LogMessage>> = OnceLock::new();
+
+/// Work for the background writer.
+enum LogMessage {
+    /// An entry already in the in-memory log, to write to disk.
+    Persist(LogEntry),
+    /// An entry from code without access to the shared state, to add to the
+    /// in-memory log as well.
+    Record(LogEntry),
+}
+
+/// The active log file, kept open between entries.
+#[derive(Default)]
+struct LogFile {
+    file: Option<File>,
+    len: u64,
+}
+
+impl LogFile {
+    /// Appends an entry, rotating the file first if it is full.
+    fn append(&mut self, entry: &LogEntry) -> io::Result<()> {
+        if self.file.is_some() && self.len >= MAX_FILE_BYTES {
+            self.file = None;
+            rotate()?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let path = log_path(0);
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                let file = OpenOptions::new().create(true).append(true).open(&path)?;
+                self.len = file.metadata()?.len();
+                self.file.insert(file)
+            }
+        };
+
+        let mut line = serde_json::to_string(entry).map_err(io::Error::other)?;
+        line.push('\n');
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            // Reopen on the next entry in case the file was moved or deleted
+            self.file = None;
+            return Err(e);
+        }
+        self.len += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Starts the thread that writes queued entries to the log files and adds
+/// [`record`]ed entries to the in-memory log.
+///
+/// A write failure is reported once in the in-memory log, and again only after
+/// writes have recovered and failed anew.
+pub fn start_writer(state: SharedState) {
+    let (tx, rx) = mpsc::channel::<LogMessage>();
+    if WRITER.set(tx).is_err() {
+        return;
+    }
+    let writer_state = state.clone();
+    let spawned = thread::Builder::new()
+        .name("LogWriter".into())
+        .spawn(move || {
+            let mut log_file = LogFile::default();
+            let mut failing = false;
+            for message in rx {
+                let entry = match message {
+                    LogMessage::Persist(entry) => entry,
+                    LogMessage::Record(entry) => {
+                        writer_state
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .push_log(entry.clone());
+                        entry
+                    }
+                };
+                match log_file.append(&entry) {
+                    Ok(()) => failing = false,
+                    Err(e) if !failing => {
+                        failing = true;
+                        writer_state
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .push_log(LogEntry::new(
+                                LogLevel::Error,
+                                None,
+                                None,
+                                format!("Failed to write log file: {}", e),
+                            ));
+                    }
+                    Err(_) => {}
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push_log(LogEntry::new(
+                LogLevel::Error,
+                None,
+                None,
+                format!("Failed to start log writer: {}", e),
+            ));
+    }
+}
+
+/// Queues an entry for the log files. Without a running writer it is only kept in memory.
+pub fn append(entry: &LogEntry) {
+    if let Some(tx) = WRITER.get() {
+        let _ = tx.send(LogMessage::Persist(entry.clone()));
+    }
+}
+
+/// Logs a message from code that has no access to the shared state, such as
+/// background cache work. It shows in the log panel shortly after.
+pub fn record(level: LogLevel, message: impl Into<String>) {
+    if let Some(tx) = WRITER.get() {
+        let _ = tx.send(LogMessage::Record(LogEntry::new(
+            level, None, None, message,
+        )));
+    }
+}
+
+/// Loads up to `limit` of the most recent entries from the log files, oldest first.
+///
+/// Lines that fail to parse (e.g. a partially written final line) are skipped.
+pub fn load_recent(limit: usize) -> VecDeque<LogEntry> {
+    let mut entries = VecDeque::with_capacity(limit);
+    for index in (0..=ROTATED_FILES).rev() {
+        let Ok(content) = fs::read_to_string(log_path(index)) else {
+            continue;
+        };
+        for line in content.lines() {
+            if let Ok(entry) = serde_json::from_str::<LogEntry>(line) {
+                if entries.len() == limit {
+                    entries.pop_front();
+                }
+                entries.push_back(entry);
+            }
+        }
+    }
+    entries
+}
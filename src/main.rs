@@ -1,14 +1,29 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod announce;
+mod audio_cache;
 mod bot;
 mod config;
+mod diagnostics;
+mod gateway;
 mod gui;
+mod invite;
+mod logging;
+mod metadata_cache;
+mod player;
+mod playlist;
+mod radio;
+mod scheduler;
 mod sources;
+mod speech;
 mod state;
 
 use crate::bot::{BotManager, ManagerCommand};
 use crate::config::ConfigManager;
+use crate::state::{MAX_LOG_ENTRIES, ToolSettings};
 use eframe::egui;
+use std::env;
+use std::process;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use tokio::sync::mpsc;
@@ -17,18 +32,30 @@ use tokio::sync::mpsc;
 /// 1. **Main Thread**: Runs the synchronous `eframe` (GUI) event loop.
 /// 2. **Supervisor Thread**: Runs the asynchronous `tokio` runtime to manage the `BotManager`.
 fn main() -> eframe::Result<()> {
+    // Headless health check: print the dependency report and exit without starting the GUI.
+    if env::args().skip(1).any(|arg| arg == "--check") {
+        #[cfg(target_os = "windows")]
+        attach_parent_console();
+        process::exit(run_check(&ConfigManager::load().tools));
+    }
+
     // Load Configuration & Initialize State
     // Persistence data (credentials, etc.) first, then hydrate the AppState.
     let config = ConfigManager::load();
-    let initial_state = ConfigManager::init_state(&config);
+    let mut initial_state = ConfigManager::init_state(&config);
+    initial_state.system_logs = logging::load_recent(MAX_LOG_ENTRIES);
 
     // Wrap state in Arc<Mutex<>> for safe concurrent access between GUI and Bot threads.
     let shared_state = Arc::new(Mutex::new(initial_state));
+    logging::start_writer(shared_state.clone());
 
     // Initialize Supervisor Communication
     // The GUI sends high-level lifecycle commands (Start/Stop Bot) to the Manager.
     let (manager_tx, manager_rx) = mpsc::channel::<ManagerCommand>(32);
 
+    // Check yt-dlp/ffmpeg once at startup so problems surface before the first track fails.
+    let _ = manager_tx.try_send(ManagerCommand::CheckDependencies);
+
     // Spawn the Background Supervisor Thread
     // Clone the Arc reference to pass shared ownership to the background thread.
     let state_for_supervisor = shared_state.clone();
@@ -64,3 +91,36 @@ fn main() -> eframe::Result<()> {
         Box::new(|cc| Ok(Box::new(gui::MusicApp::new(cc, manager_tx, shared_state)))),
     )
 }
+
+/// Attaches to the console of the shell that started the app. Release builds use the
+/// Windows GUI subsystem, so without this `--check` would print to nowhere.
+#[cfg(target_os = "windows")]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // SAFETY: AttachConsole has no preconditions. It fails harmlessly when there is
+    // no parent console or one is already attached (debug builds).
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+/// Runs the dependency health check and prints the report to stdout.
+/// Returns the process exit code: non-zero if any problem was found.
+fn run_check(tools: &ToolSettings) -> i32 {
+    let report = diagnostics::check_dependencies(tools);
+    for line in report.summary_lines() {
+        println!("{}", line);
+    }
+    if report.is_ok() {
+        println!("All dependencies OK.");
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,198 @@
+//! Metadata Cache Module
+//!
+//! Remembers the yt-dlp metadata (title, artist, duration, thumbnail) of URLs
+//! that have been played, so replaying them skips the slow `--dump-json` call.
+//! Stream URLs expire quickly and are never cached; they are resolved at play time.
+//! The cache is shared by every bot and persisted to `cache/metadata.json`.
+
+use crate::config::ConfigManager;
+use crate::logging::{self, LogLevel};
+use chrono::Local;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+/// Entries older than this are refetched, in case the title or thumbnail changed.
+const TTL_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+/// The oldest entries are dropped once the cache grows past this many URLs.
+const MAX_ENTRIES: usize = 5000;
+/// Writes wait this long after an insert, so a burst of inserts (e.g. a playlist import) is saved once.
+const SAVE_DELAY: Duration = Duration::from_secs(5);
+
+/// Query parameters that only track where a link was shared from.
+const TRACKING_PARAMS: &[&str] = &["si", "feature", "pp", "fbclid", "gclid"];
+
+static SHARED: OnceLock<Arc<MetadataCache>> = OnceLock::new();
+
+/// Display metadata for a single URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedMetadata {
+    pub title: String,
+    pub artist: Option<String>,
+    pub duration_secs: Option<f64>,
+    pub thumbnail_url: Option<String>,
+    /// Unix timestamp in milliseconds when the metadata was fetched.
+    pub fetched_ms: i64,
+}
+
+impl CachedMetadata {
+    fn is_expired(&self, now_ms: i64) -> bool {
+        now_ms - self.fetched_ms > TTL_MS
+    }
+}
+
+/// On-disk cache of URL metadata, keyed by normalized URL.
+pub struct MetadataCache {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, CachedMetadata>>,
+    /// Whether a background write is already waiting to run.
+    save_scheduled: AtomicBool,
+}
+
+impl MetadataCache {
+    /// The process-wide cache, loaded from disk on first use.
+    pub fn shared() -> Arc<MetadataCache> {
+        SHARED
+            .get_or_init(|| Arc::new(Self::load(Self::default_path())))
+            .clone()
+    }
+
+    fn default_path() -> PathBuf {
+        ConfigManager::data_dir()
+            .join("cache")
+            .join("metadata.json")
+    }
+
+    /// Loads the cache file, dropping expired entries. A missing or corrupt file yields an empty cache.
+    fn load(path: PathBuf) -> Self {
+        let now = Local::now().timestamp_millis();
+        let mut entries: HashMap<String, CachedMetadata> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        entries.retain(|_, meta| !meta.is_expired(now));
+
+        Self {
+            path,
+            entries: Mutex::new(entries),
+            save_scheduled: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns unexpired metadata for a URL.
+    pub fn get(&self, url: &str) -> Option<CachedMetadata> {
+        let now = Local::now().timestamp_millis();
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(&normalize_url(url))
+            .filter(|meta| !meta.is_expired(now))
+            .cloned()
+    }
+
+    /// Stores metadata for a URL and schedules a write of the cache to disk.
+    ///
+    /// The write happens on a background thread after `SAVE_DELAY`, keeping file I/O
+    /// off the async runtime.
+    pub fn insert(self: &Arc<Self>, url: &str, metadata: CachedMetadata) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(normalize_url(url), metadata);
+
+        if entries.len() > MAX_ENTRIES {
+            let mut by_age: Vec<(String, i64)> = entries
+                .iter()
+                .map(|(key, meta)| (key.clone(), meta.fetched_ms))
+                .collect();
+            by_age.sort_by_key(|(_, fetched)| *fetched);
+            for (key, _) in by_age.into_iter().take(entries.len() - MAX_ENTRIES) {
+                entries.remove(&key);
+            }
+        }
+
+        drop(entries);
+
+        if !self.save_scheduled.swap(true, Ordering::SeqCst) {
+            let cache = self.clone();
+            thread::spawn(move || {
+                thread::sleep(SAVE_DELAY);
+                // Inserts from here on schedule another write
+                cache.save_scheduled.store(false, Ordering::SeqCst);
+                if let Err(e) = cache.save() {
+                    logging::record(
+                        LogLevel::Warn,
+                        format!("Failed to write metadata cache: {}", e),
+                    );
+                }
+            });
+        }
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let content = {
+            let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            serde_json::to_string(&*entries)?
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+/// Reduces equivalent links to one cache key.
+///
+/// Lowercases the host, drops `www.`/`m.`/`music.` prefixes and tracking parameters,
+/// maps `youtu.be/<id>` to `youtube.com/watch?v=<id>`, and sorts the remaining query.
+/// Strings that don't parse as URLs (e.g. search terms) are only trimmed.
+pub fn normalize_url(raw: &str) -> String {
+    let trimmed = raw.trim();
+    let Ok(url) = Url::parse(trimmed) else {
+        return trimmed.to_string();
+    };
+
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    let host = ["www.", "m.", "music."]
+        .iter()
+        .find_map(|prefix| host.strip_prefix(prefix))
+        .unwrap_or(&host)
+        .to_string();
+
+    let mut path = url.path().trim_end_matches('/').to_string();
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !TRACKING_PARAMS.contains(&key.as_ref()) && !key.starts_with("utm_"))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+
+    let host = if host == "youtu.be" {
+        let id = path.trim_start_matches('/').to_string();
+        path = "/watch".to_string();
+        query.push(("v".to_string(), id));
+        "youtube.com".to_string()
+    } else {
+        host
+    };
+
+    // Only the video ID identifies a YouTube watch page; `t`, `list` etc. don't change the media.
+    if host == "youtube.com" && path == "/watch" {
+        query.retain(|(key, _)| key == "v");
+    }
+    query.sort();
+
+    let mut normalized = format!("{}{}", host, path);
+    if !query.is_empty() {
+        let pairs: Vec<String> = query
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        normalized.push('?');
+        normalized.push_str(&pairs.join("&"));
+    }
+    normalized
+}
@@ -0,0 +1,212 @@
+//! Interactive Player Message Module
+//!
+//! Renders the persistent per-guild "player" message and maps its buttons
+//! onto `BotCommand`s. The BotInstance owns posting and editing the message;
+//! this module only decides what it looks like and what each button does.
+
+use crate::state::{BotCommand, GuildState, SleepAfter};
+use chrono::Local;
+use serenity::all::{ButtonStyle, CreateActionRow, CreateButton, CreateEmbed};
+use std::time::Duration;
+
+/// Prefix shared by all player button custom IDs.
+const CUSTOM_ID_PREFIX: &str = "player:";
+
+/// Volume change applied by the volume buttons.
+const VOLUME_STEP: f32 = 0.1;
+
+/// Sleep timer started by the sleep button.
+const SLEEP_AFTER: Duration = Duration::from_secs(30 * 60);
+
+/// A button on the player message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayerButton {
+    PauseResume,
+    Skip,
+    Stop,
+    VolumeDown,
+    VolumeUp,
+    Loop,
+    Sleep,
+}
+
+impl PlayerButton {
+    const ALL: [PlayerButton; 7] = [
+        PlayerButton::PauseResume,
+        PlayerButton::Skip,
+        PlayerButton::Stop,
+        PlayerButton::VolumeDown,
+        PlayerButton::VolumeUp,
+        PlayerButton::Loop,
+        PlayerButton::Sleep,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            PlayerButton::PauseResume => "pause",
+            PlayerButton::Skip => "skip",
+            PlayerButton::Stop => "stop",
+            PlayerButton::VolumeDown => "vol_down",
+            PlayerButton::VolumeUp => "vol_up",
+            PlayerButton::Loop => "loop",
+            PlayerButton::Sleep => "sleep",
+        }
+    }
+
+    /// The component custom ID for this button.
+    pub fn custom_id(self) -> String {
+        format!("{}{}", CUSTOM_ID_PREFIX, self.key())
+    }
+
+    /// Parses a component custom ID, returning `None` for components that aren't player buttons.
+    pub fn from_custom_id(custom_id: &str) -> Option<Self> {
+        let key = custom_id.strip_prefix(CUSTOM_ID_PREFIX)?;
+        Self::ALL.into_iter().find(|b| b.key() == key)
+    }
+
+    /// Maps the button onto the command it triggers, given the guild's current state.
+    pub fn command(self, guild: &GuildState) -> BotCommand {
+        let guild_id = guild.guild_id;
+        match self {
+            PlayerButton::PauseResume if guild.is_paused => BotCommand::Resume { guild_id },
+            PlayerButton::PauseResume => BotCommand::Pause { guild_id },
+            PlayerButton::Skip => BotCommand::Skip { guild_id },
+            PlayerButton::Stop => BotCommand::Stop { guild_id },
+            PlayerButton::VolumeDown => BotCommand::Volume {
+                guild_id,
+                volume: (guild.volume - VOLUME_STEP).max(0.0),
+            },
+            PlayerButton::VolumeUp => BotCommand::Volume {
+                guild_id,
+                volume: (guild.volume + VOLUME_STEP).min(1.0),
+            },
+            PlayerButton::Loop => BotCommand::SetLoop {
+                guild_id,
+                enabled: !guild.is_looping,
+            },
+            PlayerButton::Sleep if guild.sleep_timer.is_some() => {
+                BotCommand::CancelSleepTimer { guild_id }
+            }
+            PlayerButton::Sleep => BotCommand::SetSleepTimer {
+                guild_id,
+                after: SleepAfter::Duration(SLEEP_AFTER),
+                leave: true,
+            },
+        }
+    }
+}
+
+/// The parts of a guild's state shown on the player message.
+///
+/// Compared against the last posted view so the message is only edited when it changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerView {
+    title: Option<String>,
+    url: Option<String>,
+    is_paused: bool,
+    is_looping: bool,
+    volume_pct: u32,
+    queued: usize,
+    /// When the sleep timer fires, to the minute so the message isn't edited every tick.
+    sleep: Option<String>,
+}
+
+impl PlayerView {
+    /// Captures the displayed fields from a guild's state.
+    pub fn from_guild(guild: &GuildState) -> Self {
+        Self {
+            title: guild.now_playing.as_ref().map(|t| t.title.clone()),
+            url: guild.now_playing.as_ref().map(|t| t.url.clone()),
+            is_paused: guild.is_paused,
+            is_looping: guild.is_looping,
+            volume_pct: (guild.volume * 100.0).round() as u32,
+            queued: guild.queue.len(),
+            sleep: guild
+                .sleep_timer
+                .as_ref()
+                .map(|timer| match timer.fires_at_ms {
+                    Some(at) => {
+                        let mins =
+                            ((at - Local::now().timestamp_millis()).max(0) + 59_999) / 60_000;
+                        format!("in {} min", mins)
+                    }
+                    None => timer.describe(0),
+                }),
+        }
+    }
+
+    /// Builds the player embed.
+    pub fn embed(&self) -> CreateEmbed {
+        let status = match (&self.title, self.is_paused) {
+            (None, _) => "Idle",
+            (Some(_), true) => "Paused",
+            (Some(_), false) => "Playing",
+        };
+
+        let mut embed = CreateEmbed::new()
+            .title("Player")
+            .description(self.title.as_deref().unwrap_or("Nothing is playing."))
+            .field("Status", status, true)
+            .field("Volume", format!("{}%", self.volume_pct), true)
+            .field("Loop", if self.is_looping { "On" } else { "Off" }, true)
+            .field("Up Next", format!("{} tracks", self.queued), true);
+        if let Some(sleep) = &self.sleep {
+            embed = embed.field("Sleep Timer", format!("Stopping {}", sleep), true);
+        }
+        if let Some(url) = &self.url {
+            embed = embed.url(url);
+        }
+        embed
+    }
+
+    /// Builds the button rows.
+    pub fn components(&self) -> Vec<CreateActionRow> {
+        let button = |b: PlayerButton, label: &str, style: ButtonStyle| {
+            CreateButton::new(b.custom_id()).label(label).style(style)
+        };
+        let idle = self.title.is_none();
+
+        vec![
+            CreateActionRow::Buttons(vec![
+                button(
+                    PlayerButton::PauseResume,
+                    if self.is_paused { "Resume" } else { "Pause" },
+                    ButtonStyle::Primary,
+                )
+                .disabled(idle),
+                button(PlayerButton::Skip, "Skip", ButtonStyle::Secondary).disabled(idle),
+                button(PlayerButton::Stop, "Stop", ButtonStyle::Danger).disabled(idle),
+            ]),
+            CreateActionRow::Buttons(vec![
+                button(PlayerButton::VolumeDown, "Vol -", ButtonStyle::Secondary)
+                    .disabled(self.volume_pct == 0),
+                button(PlayerButton::VolumeUp, "Vol +", ButtonStyle::Secondary)
+                    .disabled(self.volume_pct >= 100),
+                button(
+                    PlayerButton::Loop,
+                    if self.is_looping {
+                        "Loop: On"
+                    } else {
+                        "Loop: Off"
+                    },
+                    if self.is_looping {
+                        ButtonStyle::Success
+                    } else {
+                        ButtonStyle::Secondary
+                    },
+                )
+                .disabled(idle),
+                button(
+                    PlayerButton::Sleep,
+                    if self.sleep.is_some() {
+                        "Cancel Sleep"
+                    } else {
+                        "Sleep 30m"
+                    },
+                    ButtonStyle::Secondary,
+                )
+                .disabled(idle && self.sleep.is_none()),
+            ]),
+        ]
+    }
+}
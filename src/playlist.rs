@@ -0,0 +1,492 @@
+//! Playlist Import/Export Module
+//!
+//! Parses M3U/M3U8, PLS and exported JSON playlists, from local files or URLs,
+//! into track entries. Relative entries are resolved against the playlist's own
+//! location, so each entry is a local path or URL that `SourceResolver` can play.
+//! Queues and play history are exported as M3U or JSON into an `exports`
+//! directory beside the executable (or a given path), in a form the importer
+//! reads back. Exports are requested from the dashboard or with
+//! `BotCommand::ExportTracks`.
+
+use crate::config::ConfigManager;
+use crate::logging::{self, LogLevel};
+use crate::state::{GuildState, TrackMetadata};
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Version of the JSON export schema.
+const EXPORT_VERSION: u32 = 1;
+/// Time allowed to download a remote playlist.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+/// Largest playlist read, so a stream mislabelled as a playlist isn't buffered forever.
+const MAX_PLAYLIST_BYTES: u64 = 4 * 1024 * 1024;
+
+/// The JSON export schema: a named list of tracks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistFile {
+    pub version: u32,
+    pub name: String,
+    /// Unix timestamp in milliseconds.
+    pub exported_at_ms: i64,
+    pub tracks: Vec<TrackMetadata>,
+}
+
+/// File format for exports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    M3u,
+    Json,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 2] = [ExportFormat::M3u, ExportFormat::Json];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::M3u => "m3u8",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Which of a guild's track lists to export.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackList {
+    Queue,
+    History,
+}
+
+impl TrackList {
+    pub const ALL: [TrackList; 2] = [TrackList::Queue, TrackList::History];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TrackList::Queue => "Queue",
+            TrackList::History => "History",
+        }
+    }
+
+    /// The list's tracks. The queue starts with the track now playing and ends with
+    /// imported entries that are not resolved yet.
+    pub fn tracks(self, guild: &GuildState) -> Vec<TrackMetadata> {
+        match self {
+            TrackList::Queue => guild
+                .now_playing
+                .iter()
+                .chain(guild.queue.iter())
+                .chain(guild.pending_imports.iter())
+                .cloned()
+                .collect(),
+            TrackList::History => guild.history.iter().cloned().collect(),
+        }
+    }
+}
+
+/// A single track listed in a playlist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistEntry {
+    /// Local path or URL of the track.
+    pub location: String,
+    /// Title from `#EXTINF` or `TitleN=`, if given.
+    pub title: Option<String>,
+    /// Length in seconds, if given and not a live stream (-1).
+    pub duration_secs: Option<u64>,
+}
+
+/// Whether a user-supplied string looks like a playlist rather than a single track.
+pub fn is_playlist(source: &str) -> bool {
+    let path = source
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(source)
+        .to_lowercase();
+    [".m3u", ".m3u8", ".pls"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+}
+
+/// Reads a playlist from a local path or an HTTP(S) URL and parses its entries.
+///
+/// Entries of a remote playlist must be HTTP(S) URLs too: local paths and `file:`
+/// URLs are dropped, so a third-party playlist can't play files from this machine.
+pub async fn load(source: &str, client: &Client) -> Result<Vec<PlaylistEntry>> {
+    let source = source.trim();
+    let remote = source.starts_with("http://") || source.starts_with("https://");
+    let text = if remote {
+        download(source, client)
+            .await
+            .with_context(|| format!("Failed to download playlist {}", source))?
+    } else {
+        let path = source.strip_prefix("file://").unwrap_or(source);
+        let size = tokio::fs::metadata(path)
+            .await
+            .with_context(|| format!("Failed to read playlist {}", path))?
+            .len();
+        if size > MAX_PLAYLIST_BYTES {
+            bail!("Playlist {} is too large ({} bytes)", path, size);
+        }
+        let bytes = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read playlist {}", path))?;
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+
+    // HLS playlists describe one stream in segments; ffmpeg plays them as a single track.
+    if text.contains("#EXT-X-TARGETDURATION") || text.contains("#EXT-X-STREAM-INF") {
+        return Ok(vec![PlaylistEntry {
+            location: source.to_string(),
+            title: None,
+            duration_secs: None,
+        }]);
+    }
+
+    let head = text.trim_start_matches('\u{feff}').trim_start();
+    let mut entries = if head.starts_with('{') {
+        parse_json(&text)?
+    } else if head.to_lowercase().starts_with("[playlist]") {
+        parse_pls(&text)
+    } else {
+        parse_m3u(&text)
+    };
+    if entries.is_empty() {
+        bail!("No tracks found in playlist {}", source);
+    }
+
+    for entry in &mut entries {
+        entry.location = resolve_location(source, &entry.location);
+    }
+    if remote {
+        let listed = entries.len();
+        entries.retain(|entry| is_web_url(&entry.location));
+        let dropped = listed - entries.len();
+        if entries.is_empty() {
+            bail!("Playlist {} only lists local files", source);
+        }
+        if dropped > 0 {
+            logging::record(
+                LogLevel::Warn,
+                format!("Skipped {} local file entries in {}", dropped, source),
+            );
+        }
+    }
+    Ok(entries)
+}
+
+/// Downloads a remote playlist as text, within the time and size limits.
+async fn download(url: &str, client: &Client) -> Result<String> {
+    let mut response = client
+        .get(url)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())?;
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_PLAYLIST_BYTES)
+    {
+        bail!("playlist is larger than {} bytes", MAX_PLAYLIST_BYTES);
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() as u64 > MAX_PLAYLIST_BYTES {
+            bail!("playlist is larger than {} bytes", MAX_PLAYLIST_BYTES);
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Whether a location is an HTTP(S) URL.
+fn is_web_url(location: &str) -> bool {
+    Url::parse(location).is_ok_and(|u| matches!(u.scheme(), "http" | "https"))
+}
+
+/// Parses an M3U or extended M3U (`#EXTM3U`) playlist.
+pub fn parse_m3u(text: &str) -> Vec<PlaylistEntry> {
+    let mut entries = Vec::new();
+    let mut pending: Option<(Option<u64>, Option<String>)> = None;
+
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<seconds>[ attributes],<title>
+            let (head, title) = info.split_once(',').unwrap_or((info, ""));
+            let duration = head
+                .split_whitespace()
+                .next()
+                .and_then(|d| d.parse::<f64>().ok())
+                .filter(|d| *d >= 0.0)
+                .map(|d| d as u64);
+            let title = Some(title.trim().to_string()).filter(|t| !t.is_empty());
+            pending = Some((duration, title));
+        } else if !line.starts_with('#') {
+            let (duration_secs, title) = pending.take().unwrap_or_default();
+            entries.push(PlaylistEntry {
+                location: line.to_string(),
+                title,
+                duration_secs,
+            });
+        }
+    }
+    entries
+}
+
+/// Parses a JSON playlist written by [`export`].
+pub fn parse_json(text: &str) -> Result<Vec<PlaylistEntry>> {
+    let file: PlaylistFile = serde_json::from_str(text).context("Invalid JSON playlist")?;
+    Ok(file
+        .tracks
+        .into_iter()
+        .map(|track| PlaylistEntry {
+            location: track.url,
+            title: Some(track.title),
+            duration_secs: track.duration_secs,
+        })
+        .collect())
+}
+
+/// Parses a PLS playlist (`FileN=`, `TitleN=`, `LengthN=` keys), ordered by `N`.
+pub fn parse_pls(text: &str) -> Vec<PlaylistEntry> {
+    let mut by_index: BTreeMap<u32, PlaylistEntry> = BTreeMap::new();
+
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        let (field, index) = ["file", "title", "length"]
+            .iter()
+            .find_map(|field| {
+                key.strip_prefix(field)
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .map(|n| (*field, n))
+            })
+            .unwrap_or(("", 0));
+        if field.is_empty() {
+            continue;
+        }
+
+        let entry = by_index.entry(index).or_insert_with(|| PlaylistEntry {
+            location: String::new(),
+            title: None,
+            duration_secs: None,
+        });
+        match field {
+            "file" => entry.location = value.to_string(),
+            "title" => entry.title = Some(value.to_string()).filter(|t| !t.is_empty()),
+            _ => entry.duration_secs = value.parse::<u64>().ok(),
+        }
+    }
+
+    by_index
+        .into_values()
+        .filter(|entry| !entry.location.is_empty())
+        .collect()
+}
+
+/// Resolves an entry relative to the playlist it came from.
+///
+/// URLs are kept. Other entries of a remote playlist are joined onto its URL, so
+/// `/music/a.mp3` means a file on the same host. For a local playlist, absolute
+/// paths are kept and relative ones are joined onto its directory.
+fn resolve_location(playlist: &str, location: &str) -> String {
+    if Url::parse(location).is_ok_and(|u| u.scheme().len() > 1) {
+        return location.to_string();
+    }
+
+    if let Ok(base) = Url::parse(playlist)
+        && matches!(base.scheme(), "http" | "https")
+    {
+        return base
+            .join(location)
+            .map(String::from)
+            .unwrap_or_else(|_| location.to_string());
+    }
+    if Path::new(location).is_absolute() {
+        return location.to_string();
+    }
+
+    let playlist_path = playlist.strip_prefix("file://").unwrap_or(playlist);
+    Path::new(playlist_path)
+        .parent()
+        .map(|dir| dir.join(location).to_string_lossy().into_owned())
+        .unwrap_or_else(|| location.to_string())
+}
+
+/// Renders tracks as an extended M3U playlist.
+pub fn to_m3u(tracks: &[TrackMetadata]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    for track in tracks {
+        let duration = track.duration_secs.map(|d| d as i64).unwrap_or(-1);
+        let title = match &track.artist {
+            Some(artist) => format!("{} - {}", artist, track.title),
+            None => track.title.clone(),
+        };
+        out.push_str(&format!("#EXTINF:{},{}\n{}\n", duration, title, track.url));
+    }
+    out
+}
+
+/// Writes tracks to `path`, or to `exports/<name>-<timestamp>.<ext>` if none is given,
+/// and returns the file's path.
+pub fn export(
+    tracks: &[TrackMetadata],
+    name: &str,
+    format: ExportFormat,
+    path: Option<&Path>,
+) -> Result<PathBuf> {
+    let now = Local::now();
+    let content = match format {
+        ExportFormat::M3u => to_m3u(tracks),
+        ExportFormat::Json => serde_json::to_string_pretty(&PlaylistFile {
+            version: EXPORT_VERSION,
+            name: name.to_string(),
+            exported_at_ms: now.timestamp_millis(),
+            tracks: tracks.to_vec(),
+        })?,
+    };
+
+    if let Some(path) = path {
+        fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+        return Ok(path.to_path_buf());
+    }
+
+    // Keep the file name portable whatever the guild is called
+    let safe_name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let dir = ConfigManager::data_dir().join("exports");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "{}-{}.{}",
+        safe_name,
+        now.format("%Y%m%d-%H%M%S"),
+        format.extension()
+    ));
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_reads_extinf_and_skips_comments() {
+        let text = "\u{feff}#EXTM3U\n\
+            #EXTINF:213,Artist - Song\n\
+            song.mp3\n\
+            \n\
+            # a comment\n\
+            #EXTINF:-1,Live Radio\n\
+            http://radio.example/stream\n\
+            plain.ogg\n";
+        let entries = parse_m3u(text);
+        assert_eq!(
+            entries,
+            [
+                PlaylistEntry {
+                    location: "song.mp3".to_string(),
+                    title: Some("Artist - Song".to_string()),
+                    duration_secs: Some(213),
+                },
+                PlaylistEntry {
+                    location: "http://radio.example/stream".to_string(),
+                    title: Some("Live Radio".to_string()),
+                    duration_secs: None,
+                },
+                PlaylistEntry {
+                    location: "plain.ogg".to_string(),
+                    title: None,
+                    duration_secs: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn m3u_extinf_attributes_are_ignored() {
+        let entries = parse_m3u("#EXTINF:60 tvg-id=\"x\",Title\na.mp3\n");
+        assert_eq!(entries[0].duration_secs, Some(60));
+        assert_eq!(entries[0].title.as_deref(), Some("Title"));
+    }
+
+    #[test]
+    fn pls_orders_by_index_and_drops_entries_without_files() {
+        let text = "[playlist]\n\
+            File2=b.mp3\n\
+            Title2=Second\n\
+            File1=a.mp3\n\
+            Length1=30\n\
+            Title3=No file\n\
+            NumberOfEntries=3\n\
+            Version=2\n";
+        let entries = parse_pls(text);
+        assert_eq!(
+            entries,
+            [
+                PlaylistEntry {
+                    location: "a.mp3".to_string(),
+                    title: None,
+                    duration_secs: Some(30),
+                },
+                PlaylistEntry {
+                    location: "b.mp3".to_string(),
+                    title: Some("Second".to_string()),
+                    duration_secs: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn remote_entries_resolve_against_the_playlist_url() {
+        let playlist = "https://example.com/lists/mix.m3u";
+        assert_eq!(
+            resolve_location(playlist, "a.mp3"),
+            "https://example.com/lists/a.mp3"
+        );
+        assert_eq!(
+            resolve_location(playlist, "/music/a.mp3"),
+            "https://example.com/music/a.mp3"
+        );
+        // Local files named by a remote playlist are kept as-is, then dropped by `load`
+        let file_entry = resolve_location(playlist, "file:///etc/passwd");
+        assert_eq!(file_entry, "file:///etc/passwd");
+        assert!(!is_web_url(&file_entry));
+    }
+
+    #[test]
+    fn local_entries_resolve_against_the_playlist_directory() {
+        let dir = Path::new("/music/lists");
+        let playlist = dir.join("mix.m3u").to_string_lossy().into_owned();
+        assert_eq!(
+            resolve_location(&playlist, "a.mp3"),
+            dir.join("a.mp3").to_string_lossy()
+        );
+        assert_eq!(
+            resolve_location(&playlist, "https://example.com/a.mp3"),
+            "https://example.com/a.mp3"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Internet Radio Module
+//!
+//! Follows the ICY "now playing" titles of Icecast/SHOUTcast streams.
+//! Playback goes through Songbird's HTTP input or ffmpeg, neither of which
+//! hands the interleaved metadata back, so a second connection asks for it
+//! (`Icy-MetaData: 1`), discards the audio and records each `StreamTitle` for
+//! the BotInstance to show in `GuildState.now_playing`.
+//!
+//! That connection costs as much bandwidth as playback, so it is only held
+//! while the track is playing: it closes at the first metadata block after a
+//! pause, skip or end, and reopens when the track resumes.
+
+use crate::logging::{LogEntry, LogLevel};
+use crate::state::SharedState;
+use reqwest::Client;
+use songbird::tracks::{PlayMode, TrackHandle};
+use songbird::{Event, EventContext, EventHandler};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The latest stream title of each playing radio track, keyed by `TrackMetadata::uuid`.
+pub type StreamTitles = Arc<Mutex<HashMap<String, String>>>;
+
+/// Follows a radio stream's titles while its track plays, starting on each `TrackEvent::Play`.
+pub struct IcyWatcher {
+    /// Account and guild of the track, for log entries.
+    pub uuid: String,
+    pub guild_id: u64,
+    pub state: SharedState,
+    pub url: String,
+    /// `TrackMetadata::uuid` of the radio track.
+    pub track_uuid: String,
+    pub handle: TrackHandle,
+    pub client: Client,
+    pub titles: StreamTitles,
+    /// Whether a watcher connection is open; `Play` also fires when resuming from pause.
+    pub running: Arc<AtomicBool>,
+}
+
+#[async_trait::async_trait]
+impl EventHandler for IcyWatcher {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+
+        let uuid = self.uuid.clone();
+        let guild_id = self.guild_id;
+        let state = self.state.clone();
+        let url = self.url.clone();
+        let track_uuid = self.track_uuid.clone();
+        let handle = self.handle.clone();
+        let client = self.client.clone();
+        let titles = self.titles.clone();
+        let running = self.running.clone();
+        tokio::spawn(async move {
+            if let Err(e) = watch(&client, &url, &track_uuid, &handle, &titles).await {
+                state
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .log_entry(LogEntry::new(
+                        LogLevel::Warn,
+                        Some(uuid),
+                        Some(guild_id),
+                        format!("Stopped reading radio metadata for {}: {}", url, e),
+                    ));
+            }
+            running.store(false, Ordering::SeqCst);
+            // A paused track keeps its last title until it resumes
+            if handle.get_info().await.is_err() {
+                titles
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .remove(&track_uuid);
+            }
+        });
+        None
+    }
+}
+
+/// Reads the stream while the track plays, recording every title update.
+///
+/// Every `icy-metaint` audio bytes the server inserts one length byte (in units
+/// of 16 bytes) followed by that much metadata, usually `StreamTitle='...';`.
+async fn watch(
+    client: &Client,
+    url: &str,
+    track_uuid: &str,
+    handle: &TrackHandle,
+    titles: &StreamTitles,
+) -> reqwest::Result<()> {
+    let mut response = client.get(url).header("Icy-MetaData", "1").send().await?;
+    let Some(metaint) = response
+        .headers()
+        .get("icy-metaint")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+    else {
+        return Ok(());
+    };
+
+    let mut audio_left = metaint;
+    let mut meta_len: Option<usize> = None;
+    let mut block = Vec::new();
+
+    while let Some(chunk) = response.chunk().await? {
+        let mut data = &chunk[..];
+        while !data.is_empty() {
+            match meta_len {
+                None if audio_left > 0 => {
+                    let n = audio_left.min(data.len());
+                    audio_left -= n;
+                    data = &data[n..];
+                }
+                None => {
+                    meta_len = Some(data[0] as usize * 16);
+                    block.clear();
+                    data = &data[1..];
+                }
+                Some(len) => {
+                    let n = (len - block.len()).min(data.len());
+                    block.extend_from_slice(&data[..n]);
+                    data = &data[n..];
+                    if block.len() < len {
+                        continue;
+                    }
+
+                    if let Some(title) = parse_stream_title(&block) {
+                        titles
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .insert(track_uuid.to_string(), title);
+                    }
+                    meta_len = None;
+                    audio_left = metaint;
+
+                    // The track is gone once it ends or is skipped, and idle while paused
+                    match handle.get_info().await {
+                        Ok(info) if info.playing == PlayMode::Play => {}
+                        _ => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Extracts the title from an ICY metadata block such as `StreamTitle='Artist - Song';StreamUrl='';`.
+fn parse_stream_title(block: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(block);
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &text[start..];
+    let end = rest.find("';").unwrap_or(rest.trim_end_matches('\0').len());
+    let title = rest[..end].trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
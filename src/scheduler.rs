@@ -0,0 +1,439 @@
+//! Scheduler Module
+//!
+//! Jobs that run at set times, such as joining a channel and starting a playlist
+//! for a weekly event, then leaving when it ends. A job has a one-shot or
+//! cron-like schedule and a single action for one bot account. The `BotManager`
+//! checks the schedule periodically and carries out due jobs; jobs are edited in
+//! the GUI and persisted in the config file.
+//!
+//! Runs missed while the computer slept or the app was closed are caught up if
+//! they were due within the last `MAX_CATCH_UP_MINUTES`. One-shot jobs due
+//! earlier than that are disabled and reported as missed instead.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// Date format of one-shot schedules.
+pub const ONCE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// After the app was asleep or closed, missed runs are only caught up this far back.
+pub const MAX_CATCH_UP_MINUTES: i64 = 60;
+
+/// When a job runs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Schedule {
+    /// Once, at a local date and time written as `YYYY-MM-DD HH:MM`.
+    Once(String),
+    /// Whenever a five-field cron expression (`minute hour day month weekday`) matches.
+    Cron(String),
+}
+
+impl Schedule {
+    /// Checks the schedule parses, describing the problem if not.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Schedule::Once(at) => parse_once(at, &Local).map(|_| ()),
+            Schedule::Cron(expr) => CronExpr::parse(expr).map(|_| ()),
+        }
+    }
+
+    /// Whether a one-shot schedule's time is at or before `time`, so it can no longer run.
+    pub fn has_passed(&self, time: DateTime<Local>) -> bool {
+        match self {
+            Schedule::Once(at) => parse_once(at, &Local).is_ok_and(|at| at <= time),
+            Schedule::Cron(_) => false,
+        }
+    }
+
+    /// Whether the job should run at some minute in `(after, until]`.
+    pub fn fires_between<Tz: TimeZone>(&self, after: DateTime<Tz>, until: DateTime<Tz>) -> bool {
+        match self {
+            Schedule::Once(at) => {
+                parse_once(at, &until.timezone()).is_ok_and(|at| after < at && at <= until)
+            }
+            Schedule::Cron(expr) => {
+                let Ok(cron) = CronExpr::parse(expr) else {
+                    return false;
+                };
+                let earliest = until.clone() - Duration::minutes(MAX_CATCH_UP_MINUTES);
+                // Cron matches whole minutes; step from the first minute boundary after `after`
+                let Some(mut minute) = after
+                    .max(earliest)
+                    .with_second(0)
+                    .and_then(|t| t.with_nanosecond(0))
+                else {
+                    return false;
+                };
+                minute += Duration::minutes(1);
+                while minute <= until {
+                    if cron.matches(&minute) {
+                        return true;
+                    }
+                    minute += Duration::minutes(1);
+                }
+                false
+            }
+        }
+    }
+}
+
+/// What a job does, on behalf of its bot account.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum JobAction {
+    /// Start the bot.
+    StartBot,
+    /// Stop the bot.
+    StopBot,
+    /// Join a voice channel, then enqueue a track or playlist if `source` isn't empty.
+    JoinAndPlay {
+        guild_id: u64,
+        channel_id: u64,
+        source: String,
+    },
+    /// Stop playback, clear the queue and leave the voice channel.
+    StopAndLeave { guild_id: u64 },
+}
+
+impl JobAction {
+    /// A short name for the kind of action.
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobAction::StartBot => "Start bot",
+            JobAction::StopBot => "Stop bot",
+            JobAction::JoinAndPlay { .. } => "Join and play",
+            JobAction::StopAndLeave { .. } => "Stop and leave",
+        }
+    }
+}
+
+/// A scheduled action, persisted in the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScheduledJob {
+    pub uuid: String,
+    pub name: String,
+    pub enabled: bool,
+    /// The bot account the action applies to.
+    pub account_uuid: String,
+    pub schedule: Schedule,
+    pub action: JobAction,
+    /// Local time of the most recent run, for display.
+    #[serde(default)]
+    pub last_run: Option<String>,
+}
+
+impl ScheduledJob {
+    /// A new, enabled job with an empty cron schedule.
+    pub fn new(account_uuid: String) -> Self {
+        Self {
+            uuid: uuid::Uuid::new_v4().to_string(),
+            name: String::new(),
+            enabled: true,
+            account_uuid,
+            schedule: Schedule::Cron(String::new()),
+            action: JobAction::StartBot,
+            last_run: None,
+        }
+    }
+
+    /// When the job last ran, if it has.
+    pub fn last_run_time(&self) -> Option<DateTime<Local>> {
+        let at = self.last_run.as_deref()?;
+        parse_once(at, &Local).ok()
+    }
+}
+
+/// Parses a one-shot time in the given time zone (the local one outside tests).
+fn parse_once<Tz: TimeZone>(at: &str, tz: &Tz) -> Result<DateTime<Tz>, String> {
+    let naive = NaiveDateTime::parse_from_str(at.trim(), ONCE_FORMAT)
+        .map_err(|_| format!("Expected a time like 2025-01-31 20:00, got \"{}\"", at))?;
+    // A time skipped by a DST change runs an hour later instead of never
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+        .ok_or_else(|| format!("{} doesn't exist in the local time zone", at))
+}
+
+/// A parsed cron expression: the allowed values of each field.
+struct CronExpr {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    /// Whether day-of-month and weekday were both restricted, in which case
+    /// matching either is enough (as in standard cron).
+    either_day: bool,
+}
+
+impl CronExpr {
+    /// Parses `minute hour day month weekday`, each a `*`, value, range or list,
+    /// optionally with a `/step`. Weekdays run 0-6 from Sunday; 7 is also Sunday.
+    fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "Expected 5 cron fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+
+        let mut weekdays = parse_field(weekday, 0, 7, "weekday")?;
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        weekdays.truncate(7);
+
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, "minute")?,
+            hours: parse_field(hour, 0, 23, "hour")?,
+            days: parse_field(day, 1, 31, "day")?,
+            months: parse_field(month, 1, 12, "month")?,
+            weekdays,
+            either_day: day != "*" && weekday != "*",
+        })
+    }
+
+    fn matches(&self, time: &(impl Datelike + Timelike)) -> bool {
+        let day = self.days[time.day() as usize];
+        let weekday = self.weekdays[time.weekday().num_days_from_sunday() as usize];
+        let day_matches = if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        };
+
+        self.minutes[time.minute() as usize]
+            && self.hours[time.hour() as usize]
+            && self.months[time.month() as usize]
+            && day_matches
+    }
+}
+
+/// Parses one cron field into a table indexed by value, for values `min..=max`.
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<Vec<bool>, String> {
+    let invalid = || format!("Invalid cron {} field \"{}\"", name, field);
+    let mut allowed = vec![false; max as usize + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (
+                    a.parse::<u32>().map_err(|_| invalid())?,
+                    b.parse::<u32>().map_err(|_| invalid())?,
+                ),
+                // `5/15` means every 15 from 5
+                None => {
+                    let value = range.parse::<u32>().map_err(|_| invalid())?;
+                    (value, if step > 1 { max } else { value })
+                }
+            },
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, MappedLocalTime, NaiveDate, Utc};
+
+    /// A zone that springs forward from UTC to UTC+1 at 01:00 UTC on 30 March 2025,
+    /// skipping the local hour 01:00-01:59, as in the UK.
+    #[derive(Debug, Clone, Copy)]
+    struct SpringForward;
+
+    impl SpringForward {
+        fn change() -> NaiveDateTime {
+            NaiveDate::from_ymd_opt(2025, 3, 30)
+                .and_then(|d| d.and_hms_opt(1, 0, 0))
+                .unwrap()
+        }
+    }
+
+    impl TimeZone for SpringForward {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            SpringForward
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(
+            &self,
+            local: &NaiveDateTime,
+        ) -> MappedLocalTime<FixedOffset> {
+            if *local < Self::change() {
+                MappedLocalTime::Single(FixedOffset::east_opt(0).unwrap())
+            } else if *local < Self::change() + Duration::hours(1) {
+                MappedLocalTime::None
+            } else {
+                MappedLocalTime::Single(FixedOffset::east_opt(3600).unwrap())
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let offset = if *utc < Self::change() { 0 } else { 3600 };
+            FixedOffset::east_opt(offset).unwrap()
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        let naive = NaiveDateTime::parse_from_str(time, ONCE_FORMAT).unwrap();
+        Utc.from_utc_datetime(&naive)
+    }
+
+    fn allowed(table: &[bool]) -> Vec<usize> {
+        (0..table.len()).filter(|&i| table[i]).collect()
+    }
+
+    #[test]
+    fn parse_field_reads_values_ranges_lists_and_steps() {
+        assert_eq!(allowed(&parse_field("5", 0, 59, "minute").unwrap()), [5]);
+        assert_eq!(
+            allowed(&parse_field("1-3,10", 0, 59, "minute").unwrap()),
+            [1, 2, 3, 10]
+        );
+        assert_eq!(
+            allowed(&parse_field("*/20", 0, 59, "minute").unwrap()),
+            [0, 20, 40]
+        );
+        assert_eq!(
+            allowed(&parse_field("10-20/5", 0, 59, "minute").unwrap()),
+            [10, 15, 20]
+        );
+        // `a/n` runs from `a` to the end of the range
+        assert_eq!(
+            allowed(&parse_field("5/15", 0, 59, "minute").unwrap()),
+            [5, 20, 35, 50]
+        );
+        assert_eq!(
+            allowed(&parse_field("*", 1, 12, "month").unwrap()).len(),
+            12
+        );
+    }
+
+    #[test]
+    fn parse_field_rejects_invalid_fields() {
+        for field in ["60", "0-60", "5-1", "*/0", "a", "1-", "", "1,,2"] {
+            assert!(
+                parse_field(field, 0, 59, "minute").is_err(),
+                "{:?} should be rejected",
+                field
+            );
+        }
+        assert!(parse_field("0", 1, 31, "day").is_err());
+    }
+
+    #[test]
+    fn cron_treats_seven_as_sunday() {
+        let cron = CronExpr::parse("0 9 * * 7").unwrap();
+        // 2025-06-01 was a Sunday
+        assert!(cron.matches(&at("2025-06-01 09:00")));
+        assert!(!cron.matches(&at("2025-06-02 09:00")));
+        assert!(
+            CronExpr::parse("0 9 * * 0")
+                .unwrap()
+                .matches(&at("2025-06-01 09:00"))
+        );
+    }
+
+    #[test]
+    fn cron_matches_day_or_weekday_when_both_are_set() {
+        // The 15th of the month, or any Friday
+        let cron = CronExpr::parse("0 12 15 * 5").unwrap();
+        assert!(cron.matches(&at("2025-06-15 12:00")));
+        assert!(cron.matches(&at("2025-06-06 12:00")));
+        assert!(cron.matches(&at("2025-07-15 12:00")));
+        assert!(!cron.matches(&at("2025-06-14 12:00")));
+        assert!(!cron.matches(&at("2025-06-15 12:01")));
+
+        // With only one of them restricted, the other doesn't widen the match
+        let cron = CronExpr::parse("0 12 15 * *").unwrap();
+        assert!(!cron.matches(&at("2025-06-06 12:00")));
+        let cron = CronExpr::parse("0 12 * * 5").unwrap();
+        assert!(!cron.matches(&at("2025-06-15 12:00")));
+        assert!(cron.matches(&at("2025-06-20 12:00")));
+    }
+
+    #[test]
+    fn cron_fires_once_per_matching_minute() {
+        let schedule = Schedule::Cron("30 * * * *".into());
+        assert!(schedule.fires_between(at("2025-06-01 10:29"), at("2025-06-01 10:30")));
+        // The minute at `after` was already checked
+        assert!(!schedule.fires_between(at("2025-06-01 10:30"), at("2025-06-01 10:31")));
+        // Seconds into a minute still cover that minute's boundary
+        let after = at("2025-06-01 10:29") + Duration::seconds(45);
+        let until = at("2025-06-01 10:30") + Duration::seconds(15);
+        assert!(schedule.fires_between(after, until));
+    }
+
+    #[test]
+    fn cron_catches_up_only_within_the_window() {
+        let schedule = Schedule::Cron("0 8 * * *".into());
+        let after = at("2025-06-01 07:00");
+        assert!(schedule.fires_between(after, at("2025-06-01 08:30")));
+        assert!(schedule.fires_between(after, at("2025-06-01 08:59")));
+        assert!(!schedule.fires_between(after, at("2025-06-01 09:00")));
+    }
+
+    #[test]
+    fn once_fires_in_its_interval_and_then_has_passed() {
+        let schedule = Schedule::Once("2025-06-01 20:00".into());
+        let time = at("2025-06-01 20:00");
+        assert!(schedule.fires_between(time - Duration::minutes(1), time));
+        assert!(!schedule.fires_between(time, time + Duration::minutes(1)));
+        // Missed one-shot runs are caught up however late the check is
+        assert!(schedule.fires_between(time - Duration::seconds(1), time + Duration::days(1)));
+        assert!(!Schedule::Once("not a date".into()).fires_between(time, time));
+    }
+
+    #[test]
+    fn once_in_a_dst_gap_runs_an_hour_later() {
+        let skipped = parse_once("2025-03-30 01:30", &SpringForward).unwrap();
+        assert_eq!(skipped.naive_local().to_string(), "2025-03-30 02:30:00");
+
+        let before = parse_once("2025-03-30 00:59", &SpringForward).unwrap();
+        let schedule = Schedule::Once("2025-03-30 01:30".into());
+        assert!(schedule.fires_between(before, skipped));
+        assert!(!schedule.fires_between(before, skipped - Duration::minutes(1)));
+    }
+
+    #[test]
+    fn cron_steps_over_a_dst_gap() {
+        let before = parse_once("2025-03-30 00:50", &SpringForward).unwrap();
+        let after_gap = parse_once("2025-03-30 02:10", &SpringForward).unwrap();
+        // Only 20 real minutes pass, so the 02:05 run fires without any minute repeating
+        assert_eq!((after_gap - before).num_minutes(), 20);
+        assert!(Schedule::Cron("5 2 * * *".into()).fires_between(before, after_gap));
+        assert!(!Schedule::Cron("0 0 * * *".into()).fires_between(before, after_gap));
+    }
+
+    #[test]
+    fn validate_reports_bad_schedules() {
+        assert!(Schedule::Once("2025-06-01 20:00".into()).validate().is_ok());
+        assert!(Schedule::Once("2025-06-01".into()).validate().is_err());
+        assert!(Schedule::Cron("0 9 * * 1-5".into()).validate().is_ok());
+        assert!(Schedule::Cron("0 9 * *".into()).validate().is_err());
+    }
+}
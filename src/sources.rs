@@ -1,18 +1,40 @@
 //! Audio Source Resolution Module
 //!
 //! Handles the parsing of URLs and the creation of playable audio sources.
-//! 0. Injects bundled dependencies into path (ffmpeg, etc.).
-//! 1. Fetches metadata and stream URL via `yt-dlp`.
-//! 2. Streams audio via `ffmpeg` using the direct URL.
+//! Each kind of source is a [`SourceProvider`]; the [`SourceResolver`] asks them
+//! in priority order:
+//! 1. The offline audio cache, for tracks transcoded on an earlier play.
+//! 2. Local files, played through `ffmpeg`.
+//! 3. Direct audio URLs and internet radio, detected by file extension.
+//! 4. `yt-dlp` for everything else: metadata (or cached metadata), then audio
+//!    streamed through `ffmpeg`, optionally transcoded into the offline cache.
+//!
+//! Text that no provider accepts is searched for, and the top hit is played.
+//! Bundled dependencies (ffmpeg, etc.) are injected into PATH first.
+//!
+//! Both processes' stderr is captured and classified into a [`SourceError`],
+//! so failures such as region locks or age gates can be reported precisely.
 
-use anyhow::{Context, Result, anyhow};
-use reqwest::Client;
+use crate::audio_cache::AudioCache;
+use crate::logging::{self, LogLevel};
+use crate::metadata_cache::{CachedMetadata, MetadataCache};
+use crate::state::{AudioCacheSettings, ToolSettings};
+use anyhow::{Context, Result};
+use chrono::Local;
+use reqwest::{Client, StatusCode};
 use serde::Deserialize;
-use songbird::input::{ChildContainer, Input};
+use songbird::input::{AudioStream, ChildContainer, HttpRequest, Input, LiveInput};
+use std::collections::VecDeque;
 use std::env;
-use std::process::{Command, Stdio};
-use std::sync::Once;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStderr, Command, Stdio};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex, Once};
 use std::time::Duration;
+use symphonia::core::io::{MediaSource, ReadOnlySource};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
@@ -22,125 +44,965 @@ const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 static INIT_PATH: Once = Once::new();
 
+/// Longer tracks (mixes, podcasts) aren't worth the disk space in the offline cache.
+const MAX_CACHED_TRACK_SECS: f64 = 20.0 * 60.0;
+
+/// Prepends the bundled `bin/` directory to PATH, once per process.
 fn inject_local_binaries() {
     INIT_PATH.call_once(|| {
-        if let Ok(path_var) = env::var("PATH") {
-            if let Ok(cwd) = env::current_exe() {
-                if let Some(parent) = cwd.parent() {
-                    let bin_dir = parent.join("bin");
-                    if bin_dir.exists() {
-                        let new_path = format!("{};{}", bin_dir.display(), path_var);
-                        unsafe {
-                            env::set_var("PATH", new_path);
-                        }
-                    }
+        let Some(bin_dir) = bundled_bin_dir().filter(|dir| dir.exists()) else {
+            return;
+        };
+        let existing = env::var_os("PATH").unwrap_or_default();
+        let dirs = std::iter::once(bin_dir).chain(env::split_paths(&existing));
+        match env::join_paths(dirs) {
+            Ok(new_path) => unsafe {
+                env::set_var("PATH", new_path);
+            },
+            Err(e) => eprintln!("Failed to add bin/ to PATH: {}", e),
+        }
+    });
+}
+
+/// The `bin/` directory beside the executable, where bundled tools are kept.
+pub fn bundled_bin_dir() -> Option<PathBuf> {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("bin")))
+}
+
+/// The yt-dlp executable to run: the configured path, or the bare name resolved via PATH.
+pub fn yt_dlp_program(tools: &ToolSettings) -> PathBuf {
+    tools
+        .yt_dlp_path
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("yt-dlp"))
+}
+
+/// The ffmpeg executable to run: the configured path, or the bare name resolved via PATH.
+pub fn ffmpeg_program(tools: &ToolSettings) -> PathBuf {
+    tools
+        .ffmpeg_path
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("ffmpeg"))
+}
+
+/// The user-configured yt-dlp arguments: format, cookies, proxy, then free-form extras.
+fn yt_dlp_user_args(tools: &ToolSettings) -> Vec<String> {
+    let mut args = vec!["-f".to_string(), tools.format.clone()];
+    if let Some(cookies) = &tools.cookies_file {
+        args.extend(["--cookies".to_string(), cookies.clone()]);
+    }
+    if let Some(proxy) = &tools.proxy {
+        args.extend(["--proxy".to_string(), proxy.clone()]);
+    }
+    args.extend(split_args(&tools.extra_args));
+    args
+}
+
+/// Splits a command line into arguments. Whitespace separates them, and single or double
+/// quotes group text containing spaces. Backslashes are kept as-is, so Windows paths work.
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
                 }
             }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
         }
-    });
+    }
+    // An unclosed quote runs to the end of the line
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// Builds a blocking command that won't open a console window on Windows.
+pub fn background_command(program: impl AsRef<OsStr>) -> Command {
+    #[cfg_attr(not(target_os = "windows"), allow(unused_mut))]
+    let mut cmd = Command::new(program);
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+/// A classified failure from yt-dlp or ffmpeg.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceError {
+    /// The media was removed, is private, or the URL points at nothing.
+    Unavailable,
+    /// The media is blocked in the host's region.
+    GeoBlocked,
+    /// The media requires signing in to confirm age.
+    AgeRestricted,
+    /// The host is rejecting requests for being too frequent (HTTP 429).
+    RateLimited,
+    /// The host refused access to the stream (HTTP 403).
+    Forbidden,
+    /// The named binary could not be found on PATH or in `bin/`.
+    BinaryMissing(&'static str),
+    /// Any other failure, with the most relevant stderr line.
+    Other(String),
+}
+
+impl SourceError {
+    /// Classifies a process's stderr output by matching well-known yt-dlp/ffmpeg messages.
+    pub fn from_stderr(stderr: &str) -> Self {
+        let lower = stderr.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+
+        if has(&[
+            "not available in your country",
+            "geo restrict",
+            "geo-restrict",
+            "blocked it in your country",
+        ]) {
+            SourceError::GeoBlocked
+        } else if has(&[
+            "confirm your age",
+            "age-restricted",
+            "age restricted",
+            "inappropriate for some users",
+        ]) {
+            SourceError::AgeRestricted
+        } else if has(&[
+            "http error 429",
+            "too many requests",
+            "rate-limit",
+            "rate limit",
+        ]) {
+            SourceError::RateLimited
+        } else if has(&["http error 403", "403 forbidden", "server returned 403"]) {
+            SourceError::Forbidden
+        } else if has(&[
+            "video unavailable",
+            "is private",
+            "has been removed",
+            "http error 404",
+            "404 not found",
+            "unsupported url",
+            "does not exist",
+        ]) {
+            SourceError::Unavailable
+        } else {
+            let line = stderr
+                .lines()
+                .rev()
+                .map(str::trim)
+                .find(|l| !l.is_empty())
+                .unwrap_or("unknown error");
+            SourceError::Other(line.to_string())
+        }
+    }
+
+    /// Maps a spawn failure, treating a missing executable as `BinaryMissing`.
+    fn from_spawn(binary: &'static str, err: io::Error) -> anyhow::Error {
+        if err.kind() == io::ErrorKind::NotFound {
+            SourceError::BinaryMissing(binary).into()
+        } else {
+            anyhow::Error::new(err).context(format!("Failed to run {}", binary))
+        }
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Unavailable => write!(f, "the media is unavailable or private"),
+            SourceError::GeoBlocked => write!(f, "the media is blocked in this region"),
+            SourceError::AgeRestricted => write!(f, "the media is age-restricted"),
+            SourceError::RateLimited => write!(f, "the host is rate-limiting requests"),
+            SourceError::Forbidden => write!(f, "the host refused access (HTTP 403)"),
+            SourceError::BinaryMissing(bin) => write!(f, "{} was not found", bin),
+            SourceError::Other(line) => write!(f, "{}", line),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+/// The last lines a streaming process wrote to stderr, kept for diagnosing playback failures.
+#[derive(Debug, Clone, Default)]
+pub struct StderrTail(Arc<Mutex<VecDeque<String>>>);
+
+impl StderrTail {
+    const MAX_LINES: usize = 20;
+
+    /// Drains a process's stderr on a background thread so it never blocks on a full buffer.
+    ///
+    /// Several processes of a pipeline can share one tail.
+    fn attach(&self, stderr: ChildStderr) {
+        let lines = self.0.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                let mut lines = lines.lock().unwrap_or_else(|e| e.into_inner());
+                if lines.len() == Self::MAX_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(line);
+            }
+        });
+    }
+
+    /// Classifies whatever the process has reported, or `None` if it reported nothing.
+    pub fn classify(&self) -> Option<SourceError> {
+        let lines = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.is_empty() {
+            return None;
+        }
+        let text = lines.iter().cloned().collect::<Vec<_>>().join("\n");
+        Some(SourceError::from_stderr(&text))
+    }
 }
 
 pub struct ResolvedSource {
+    /// The URL or path that was played; for a search, the top hit.
+    pub url: String,
     pub source: Input,
+    /// Captured stderr of the streaming process.
+    pub diagnostics: StderrTail,
     pub title: String,
+    pub artist: Option<String>,
     pub duration: Option<Duration>,
+    pub thumbnail_url: Option<String>,
+    /// The source is a radio stream carrying ICY "now playing" metadata.
+    pub icy_metadata: bool,
+}
+
+impl ResolvedSource {
+    fn new(url: &str, source: Input, diagnostics: StderrTail, metadata: CachedMetadata) -> Self {
+        Self {
+            url: url.to_string(),
+            source,
+            diagnostics,
+            title: metadata.title,
+            artist: metadata.artist,
+            duration: metadata.duration_secs.map(Duration::from_secs_f64),
+            thumbnail_url: metadata.thumbnail_url,
+            icy_metadata: false,
+        }
+    }
+
+    /// Wraps a playback pipeline in a Songbird input, copying its output to the
+    /// offline cache transcoder if one was started.
+    fn from_caching_pipeline(
+        url: &str,
+        children: Vec<Child>,
+        cache_copy: Option<SyncSender<Vec<u8>>>,
+        diagnostics: StderrTail,
+        metadata: CachedMetadata,
+    ) -> Self {
+        let Some(copy) = cache_copy else {
+            return Self::from_pipeline(url, children, diagnostics, metadata);
+        };
+        let reader = CachingPipeline {
+            pipeline: ChildContainer::from(children),
+            copy: Some(copy),
+        };
+        let stream = AudioStream {
+            input: Box::new(ReadOnlySource::new(reader)) as Box<dyn MediaSource>,
+            hint: None,
+        };
+        Self::new(
+            url,
+            Input::Live(LiveInput::Raw(stream), None),
+            diagnostics,
+            metadata,
+        )
+    }
+
+    /// Wraps a spawned process pipeline (read from the last process) in a Songbird input.
+    fn from_pipeline(
+        url: &str,
+        children: Vec<Child>,
+        diagnostics: StderrTail,
+        metadata: CachedMetadata,
+    ) -> Self {
+        Self::new(
+            url,
+            Input::from(ChildContainer::from(children)),
+            diagnostics,
+            metadata,
+        )
+    }
+}
+
+/// Content types Songbird can decode itself, so the stream skips ffmpeg.
+const SONGBIRD_TYPES: &[&str] = &[
+    "audio/ogg",
+    "application/ogg",
+    "audio/opus",
+    "audio/webm",
+    "audio/flac",
+    "audio/x-flac",
+    "audio/wav",
+    "audio/x-wav",
+    "audio/wave",
+];
+
+/// Playlist content types, which are not audio despite the `audio/` prefix.
+const PLAYLIST_TYPES: &[&str] = &[
+    "audio/x-mpegurl",
+    "audio/mpegurl",
+    "application/vnd.apple.mpegurl",
+    "application/x-mpegurl",
+    "audio/x-scpls",
+];
+
+/// Time allowed for a direct URL to answer the content-type probe.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// File extensions of audio served directly, played without yt-dlp.
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "ogg", "oga", "opus", "flac", "wav", "m4a", "aac", "weba", "mka",
+];
+
+/// Sites that serve pages rather than audio files, left to yt-dlp whatever their URLs look like.
+const PAGE_HOSTS: &[&str] = &[
+    "youtube.com",
+    "youtu.be",
+    "soundcloud.com",
+    "bandcamp.com",
+    "mixcloud.com",
+    "vimeo.com",
+    "twitch.tv",
+    "dailymotion.com",
+    "bilibili.com",
+    "nicovideo.jp",
+];
+
+/// What probing a URL revealed about a direct audio stream.
+struct StreamProbe {
+    /// Songbird can decode the stream without ffmpeg.
+    native: bool,
+    /// Station name from the `icy-name` header.
+    station: Option<String>,
+    /// The server interleaves ICY metadata when asked (`icy-metaint` header).
+    icy_metadata: bool,
 }
 
 #[derive(Deserialize)]
 struct YtDlpMetadata {
     title: Option<String>,
+    artist: Option<String>,
+    uploader: Option<String>,
     duration: Option<f64>,
+    thumbnail: Option<String>,
     url: Option<String>, // Direct stream URL
 }
 
-pub struct SourceResolver {
-    _http_client: Client,
+impl YtDlpMetadata {
+    /// Keeps the display fields for the metadata cache.
+    fn into_cached(self) -> CachedMetadata {
+        CachedMetadata {
+            title: self.title.unwrap_or_else(|| "Unknown Title".to_string()),
+            // Music tracks carry an artist; fall back to the uploader for everything else.
+            artist: self.artist.or(self.uploader),
+            duration_secs: self.duration,
+            thumbnail_url: self.thumbnail,
+            fetched_ms: Local::now().timestamp_millis(),
+        }
+    }
 }
 
-impl SourceResolver {
-    pub fn new() -> Self {
-        inject_local_binaries();
-        Self {
-            _http_client: Client::new(),
+/// Builds an ffmpeg command reading `input`: a URL, a local file, or `pipe:0`.
+/// Callers append the output arguments.
+fn ffmpeg_reading(tools: &ToolSettings, input: &str) -> Command {
+    let mut cmd = background_command(ffmpeg_program(tools));
+    cmd.args([
+        "-hide_banner",
+        "-loglevel",
+        "error", // Only errors reach stderr, so any output is diagnostic
+    ]);
+    // Network options are rejected for other inputs, so only HTTP(S) gets them
+    if input.starts_with("http") {
+        // Stream URLs are often bound to the IP that resolved them, so reuse yt-dlp's proxy.
+        if let Some(proxy) = tools.proxy.as_deref().filter(|p| p.starts_with("http")) {
+            cmd.args(["-http_proxy", proxy]);
         }
+        cmd.args([
+            "-reconnect",
+            "1",
+            "-reconnect_streamed",
+            "1",
+            "-reconnect_delay_max",
+            "5",
+        ]);
     }
+    cmd.args(["-i", input]);
+    cmd
+}
 
-    pub async fn resolve(&self, url: &str) -> Result<ResolvedSource> {
-        // Fetch Metadata & Stream URL
-        let metadata = self.fetch_metadata(url).await?;
+/// Builds an ffmpeg command converting `input` to WAV on stdout for playback.
+fn ffmpeg_playback(tools: &ToolSettings, input: &str) -> Command {
+    let mut cmd = ffmpeg_reading(tools, input);
+    cmd.args(["-f", "wav"]); // Output as WAV (header + PCM) for easy probing
+    cmd.args(["-ar", "48000"]); // Standard sample rate
+    cmd.args(["-ac", "2"]); // Stereo
+    cmd.args(["-map", "a"]); // Map audio only
+    cmd.arg("-"); // Output to stdout
+    cmd
+}
 
-        // Create Audio Stream via FFmpeg
-        let mut cmd = if let Some(stream_url) = &metadata.url {
-            let mut c = Command::new("ffmpeg");
-            c.args([
-                "-reconnect",
-                "1",
-                "-reconnect_streamed",
-                "1",
-                "-reconnect_delay_max",
-                "5",
-                "-i",
-                stream_url,
-                "-f",
-                "wav", // Output as WAV (header + PCM) for easy probing
-                "-ar",
-                "48000", // Standard sample rate
-                "-ac",
-                "2", // Stereo
-                "-map",
-                "a", // Map audio only
-                "-", // Output to stdout
-            ]);
-            c
-        } else {
-            // Fallback: If no direct URL, let yt-dlp pipe it
-            let mut c = Command::new("yt-dlp");
-            c.args([
-                "-f",
-                "bestaudio/best",
-                "-o",
-                "-",
-                "-q",
-                "--no-warnings",
-                url,
-            ]);
-            c
-        };
+/// Builds a yt-dlp command that downloads `url` to stdout.
+fn yt_dlp_download(tools: &ToolSettings, url: &str) -> Command {
+    let mut cmd = background_command(yt_dlp_program(tools));
+    cmd.args(yt_dlp_user_args(tools));
+    cmd.args(["-o", "-", "-q", "--no-warnings", url]);
+    cmd
+}
 
-        // Suppress Window on Windows
-        #[cfg(target_os = "windows")]
-        cmd.creation_flags(CREATE_NO_WINDOW);
+/// Spawns a process with piped stdout, capturing its stderr into `diagnostics`.
+fn spawn_piped(cmd: &mut Command, binary: &'static str, diagnostics: &StderrTail) -> Result<Child> {
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| SourceError::from_spawn(binary, e))?;
+    if let Some(stderr) = child.stderr.take() {
+        diagnostics.attach(stderr);
+    }
+    Ok(child)
+}
+
+/// Spawns yt-dlp downloading `url` piped into the ffmpeg command built by `ffmpeg`.
+fn spawn_download_pipeline(
+    tools: &ToolSettings,
+    url: &str,
+    ffmpeg: impl FnOnce(&str) -> Command,
+    diagnostics: &StderrTail,
+) -> Result<Vec<Child>> {
+    let mut downloader = spawn_piped(&mut yt_dlp_download(tools, url), "yt-dlp", diagnostics)?;
+    let download = downloader
+        .stdout
+        .take()
+        .context("yt-dlp stdout was not captured")?;
+
+    let mut converter = ffmpeg("pipe:0");
+    converter.stdin(Stdio::from(download));
+    let converter = spawn_piped(&mut converter, "ffmpeg", diagnostics)?;
+    Ok(vec![downloader, converter])
+}
+
+/// The local file a source refers to, if it is an existing path or a `file://` URL.
+fn local_file(source: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(source.strip_prefix("file://").unwrap_or(source));
+    path.is_file().then_some(path)
+}
+
+/// Whether input reads as a file path rather than search terms: a `file:` URL, an
+/// absolute or explicitly relative path, or a separator-joined word like `music/song.mp3`.
+///
+/// Free text such as "AC/DC Thunderstruck" has spaces and is still searched.
+fn looks_like_path(input: &str) -> bool {
+    let bytes = input.as_bytes();
+    let drive = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && matches!(bytes[2], b'/' | b'\\');
+    input.starts_with("file:")
+        || input.starts_with(['/', '\\', '~'])
+        || input.starts_with("./")
+        || input.starts_with("../")
+        || drive
+        || (input.contains(['/', '\\']) && !input.contains(char::is_whitespace))
+}
+
+/// A display title for a direct URL without metadata: its file name, or the whole URL.
+fn direct_title(url: &str) -> String {
+    url.split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(url)
+        .to_string()
+}
+
+/// Chunks of playback output buffered for the cache transcoder before caching is abandoned.
+const CACHE_BUFFER_CHUNKS: usize = 256;
+
+/// A playback pipeline whose WAV output is also copied to an Opus transcoder for the
+/// offline cache, so caching a track reuses the download already made for playback.
+struct CachingPipeline {
+    pipeline: ChildContainer,
+    /// Copies of the bytes read, for the transcoder thread. An empty chunk marks a
+    /// complete track; dropping the sender without one abandons the cache file.
+    copy: Option<SyncSender<Vec<u8>>>,
+}
+
+impl Read for CachingPipeline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.pipeline.read(buf)?;
+        if read == 0 {
+            // Output ended; the copy is only complete if every process succeeded
+            if let Some(copy) = self.copy.take() {
+                let mut succeeded = true;
+                for child in self.pipeline.0.iter_mut().rev() {
+                    succeeded &= child.wait().is_ok_and(|status| status.success());
+                }
+                if succeeded {
+                    let _ = copy.send(Vec::new());
+                }
+            }
+        } else if let Some(copy) = &self.copy {
+            // A stalled transcoder must never hold up playback, so give up on caching instead
+            if copy.try_send(buf[..read].to_vec()).is_err() {
+                self.copy = None;
+            }
+        }
+        Ok(read)
+    }
+}
+
+/// Starts an Opus transcoder for a track about to play, returning where to send the
+/// playback output. Returns `None` if the track is already cached or being cached.
+fn start_cache_transcode(
+    tools: &ToolSettings,
+    settings: &AudioCacheSettings,
+    url: &str,
+    metadata: CachedMetadata,
+) -> Option<SyncSender<Vec<u8>>> {
+    let cache = AudioCache::shared();
+    let temp = cache.begin(url)?;
+
+    let mut cmd = ffmpeg_reading(tools, "pipe:0");
+    cmd.args(["-vn", "-c:a", "libopus", "-b:a", "128k", "-f", "ogg", "-y"]);
+    cmd.arg(&temp);
+    cmd.stdin(Stdio::piped());
+    let diagnostics = StderrTail::default();
+    let mut transcoder = match spawn_piped(&mut cmd, "ffmpeg", &diagnostics) {
+        Ok(child) => child,
+        Err(e) => {
+            logging::record(
+                LogLevel::Warn,
+                format!("Failed to cache audio for {}: {:#}", url, e),
+            );
+            cache.abort(url, &temp);
+            return None;
+        }
+    };
+
+    let (tx, rx) = mpsc::sync_channel::<Vec<u8>>(CACHE_BUFFER_CHUNKS);
+    let url = url.to_string();
+    let max_bytes = settings.max_size_mb * 1024 * 1024;
+    std::thread::spawn(move || {
+        let mut complete = false;
+        if let Some(mut stdin) = transcoder.stdin.take() {
+            for chunk in rx {
+                if chunk.is_empty() {
+                    complete = true;
+                    break;
+                }
+                if stdin.write_all(&chunk).is_err() {
+                    break;
+                }
+            }
+        }
+        // A skipped track leaves a truncated file, so don't let it finish
+        if !complete {
+            let _ = transcoder.kill();
+        }
+        let transcoded = transcoder.wait().is_ok_and(|status| status.success());
+
+        if complete && transcoded {
+            match cache.finish(&url, &temp, metadata, max_bytes) {
+                Ok(()) => return,
+                Err(e) => logging::record(
+                    LogLevel::Warn,
+                    format!("Failed to cache audio for {}: {}", url, e),
+                ),
+            }
+        } else if complete {
+            let reason = diagnostics
+                .classify()
+                .map(|e| e.to_string())
+                .unwrap_or_else(|| "transcode exited with an error".to_string());
+            logging::record(
+                LogLevel::Warn,
+                format!("Failed to cache audio for {}: {}", url, reason),
+            );
+        }
+        cache.abort(&url, &temp);
+    });
+    Some(tx)
+}
+
+/// Settings and shared resources a provider may use while resolving.
+pub struct ProviderContext<'a> {
+    pub tools: &'a ToolSettings,
+    pub audio_cache: &'a AudioCacheSettings,
+    pub http_client: &'a Client,
+}
+
+/// A search hit: a playable URL and its display metadata.
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub url: String,
+    pub metadata: CachedMetadata,
+}
+
+/// A way of turning a URL into playable audio.
+///
+/// The [`SourceResolver`] asks providers in descending priority order and uses the
+/// first whose [`matches`](SourceProvider::matches) accepts the URL.
+#[async_trait::async_trait]
+pub trait SourceProvider: Send + Sync {
+    /// Short name used in logs.
+    fn name(&self) -> &'static str;
+
+    /// Providers with higher priorities are asked first.
+    fn priority(&self) -> i32;
+
+    /// Whether this provider can play the URL.
+    async fn matches(&self, url: &str, ctx: &ProviderContext<'_>) -> bool;
+
+    /// Display metadata for the URL, without starting playback.
+    async fn fetch_metadata(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<CachedMetadata>;
+
+    /// Creates the Songbird input for the URL.
+    async fn create_input(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<ResolvedSource>;
+
+    /// Looks up tracks matching free text. Providers without search return nothing.
+    async fn search(
+        &self,
+        _query: &str,
+        _limit: usize,
+        _ctx: &ProviderContext<'_>,
+    ) -> Result<Vec<SearchResult>> {
+        Ok(Vec::new())
+    }
+
+    /// Tracks related to the URL, for autoplay. Providers without recommendations return nothing.
+    async fn related(&self, _url: &str, _ctx: &ProviderContext<'_>) -> Result<Vec<SearchResult>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Metadata for a source that only has a name to show.
+fn titled(title: String, duration_secs: Option<f64>) -> CachedMetadata {
+    CachedMetadata {
+        title,
+        artist: None,
+        duration_secs,
+        thumbnail_url: None,
+        fetched_ms: Local::now().timestamp_millis(),
+    }
+}
+
+/// Plays tracks from the offline audio cache; they need no network at all.
+pub struct AudioCacheProvider;
+
+#[async_trait::async_trait]
+impl SourceProvider for AudioCacheProvider {
+    fn name(&self) -> &'static str {
+        "audio cache"
+    }
+
+    fn priority(&self) -> i32 {
+        100
+    }
+
+    async fn matches(&self, url: &str, ctx: &ProviderContext<'_>) -> bool {
+        ctx.audio_cache.enabled && AudioCache::shared().contains(url)
+    }
+
+    async fn fetch_metadata(
+        &self,
+        url: &str,
+        _ctx: &ProviderContext<'_>,
+    ) -> Result<CachedMetadata> {
+        AudioCache::shared()
+            .lookup(url)
+            .map(|(_, metadata)| metadata)
+            .ok_or_else(|| SourceError::Unavailable.into())
+    }
+
+    async fn create_input(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<ResolvedSource> {
+        let (path, metadata) = AudioCache::shared()
+            .lookup(url)
+            .ok_or(SourceError::Unavailable)?;
+        let diagnostics = StderrTail::default();
+        let mut ffmpeg = ffmpeg_playback(ctx.tools, &path.to_string_lossy());
+        let child = spawn_piped(&mut ffmpeg, "ffmpeg", &diagnostics)?;
+        Ok(ResolvedSource::from_pipeline(
+            url,
+            vec![child],
+            diagnostics,
+            metadata,
+        ))
+    }
+}
+
+/// Generates a sine tone for `tone://<hz>?secs=<n>` URLs.
+///
+/// Stands in for real sources when exercising the queue and player without
+/// network access or external tools, so only tests register it.
+#[cfg(test)]
+pub struct ToneProvider;
+
+#[cfg(test)]
+impl ToneProvider {
+    const SAMPLE_RATE: u32 = 48_000;
+    const DEFAULT_HZ: f64 = 440.0;
+    const DEFAULT_SECS: f64 = 5.0;
+    /// Longest tone generated, since the whole track is held in memory.
+    const MAX_SECS: f64 = 600.0;
+
+    /// The frequency and length a tone URL asks for.
+    fn parse(url: &str) -> Result<(f64, f64)> {
+        let parsed = reqwest::Url::parse(url).context("Invalid tone URL")?;
+        let hz = parsed
+            .host_str()
+            .and_then(|h| h.parse::<f64>().ok())
+            .filter(|hz| (20.0..=20_000.0).contains(hz))
+            .unwrap_or(Self::DEFAULT_HZ);
+        let secs = parsed
+            .query_pairs()
+            .find(|(key, _)| key == "secs")
+            .and_then(|(_, value)| value.parse::<f64>().ok())
+            .filter(|secs| *secs > 0.0)
+            .unwrap_or(Self::DEFAULT_SECS)
+            .min(Self::MAX_SECS);
+        Ok((hz, secs))
+    }
+
+    /// Renders a 16-bit stereo WAV file of the tone at a quarter of full scale.
+    fn render_wav(hz: f64, secs: f64) -> Vec<u8> {
+        let frames = (secs * Self::SAMPLE_RATE as f64) as u32;
+        let data_len = frames * 4;
+
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&2u16.to_le_bytes()); // Stereo
+        wav.extend_from_slice(&Self::SAMPLE_RATE.to_le_bytes());
+        wav.extend_from_slice(&(Self::SAMPLE_RATE * 4).to_le_bytes()); // Byte rate
+        wav.extend_from_slice(&4u16.to_le_bytes()); // Block align
+        wav.extend_from_slice(&16u16.to_le_bytes()); // Bits per sample
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
 
-        // Pipe Output
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::null());
+        let step = std::f64::consts::TAU * hz / Self::SAMPLE_RATE as f64;
+        for frame in 0..frames {
+            let sample = ((frame as f64 * step).sin() * i16::MAX as f64 * 0.25) as i16;
+            wav.extend_from_slice(&sample.to_le_bytes());
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        wav
+    }
+}
 
-        let child = cmd
-            .spawn()
-            .context("Failed to spawn audio stream process (ffmpeg/yt-dlp)")?;
+#[cfg(test)]
+#[async_trait::async_trait]
+impl SourceProvider for ToneProvider {
+    fn name(&self) -> &'static str {
+        "tone"
+    }
 
-        // Wrap in Songbird Input
-        let source = Input::from(ChildContainer::from(child));
+    fn priority(&self) -> i32 {
+        90
+    }
 
-        Ok(ResolvedSource {
+    async fn matches(&self, url: &str, _ctx: &ProviderContext<'_>) -> bool {
+        url.starts_with("tone://")
+    }
+
+    async fn fetch_metadata(
+        &self,
+        url: &str,
+        _ctx: &ProviderContext<'_>,
+    ) -> Result<CachedMetadata> {
+        let (hz, secs) = Self::parse(url)?;
+        Ok(titled(format!("Test tone ({} Hz)", hz), Some(secs)))
+    }
+
+    async fn create_input(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<ResolvedSource> {
+        let (hz, secs) = Self::parse(url)?;
+        let metadata = self.fetch_metadata(url, ctx).await?;
+        let source = Input::from(Self::render_wav(hz, secs));
+        Ok(ResolvedSource::new(
+            url,
             source,
-            title: metadata
-                .title
-                .unwrap_or_else(|| "Unknown Title".to_string()),
-            duration: metadata.duration.map(Duration::from_secs_f64),
+            StderrTail::default(),
+            metadata,
+        ))
+    }
+}
+
+/// Plays local files (e.g. from imported playlists) straight through ffmpeg.
+pub struct LocalFileProvider;
+
+#[async_trait::async_trait]
+impl SourceProvider for LocalFileProvider {
+    fn name(&self) -> &'static str {
+        "local file"
+    }
+
+    fn priority(&self) -> i32 {
+        80
+    }
+
+    async fn matches(&self, url: &str, _ctx: &ProviderContext<'_>) -> bool {
+        local_file(url).is_some()
+    }
+
+    async fn fetch_metadata(
+        &self,
+        url: &str,
+        _ctx: &ProviderContext<'_>,
+    ) -> Result<CachedMetadata> {
+        let path = local_file(url).ok_or(SourceError::Unavailable)?;
+        let title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| url.to_string());
+        Ok(titled(title, None))
+    }
+
+    async fn create_input(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<ResolvedSource> {
+        let path = local_file(url).ok_or(SourceError::Unavailable)?;
+        let metadata = self.fetch_metadata(url, ctx).await?;
+        let diagnostics = StderrTail::default();
+        let mut ffmpeg = ffmpeg_playback(ctx.tools, &path.to_string_lossy());
+        let child = spawn_piped(&mut ffmpeg, "ffmpeg", &diagnostics)?;
+        Ok(ResolvedSource::from_pipeline(
+            url,
+            vec![child],
+            diagnostics,
+            metadata,
+        ))
+    }
+}
+
+/// Plays direct audio URLs and internet radio, recognised by file extension, without yt-dlp.
+///
+/// Streams without an audio extension are left to yt-dlp, whose generic extractor plays them too.
+pub struct DirectHttpProvider;
+
+impl DirectHttpProvider {
+    /// Requests the URL and inspects its headers, the only request made before playback.
+    ///
+    /// `Icy-MetaData` is requested so radio servers reveal whether they send track titles.
+    async fn probe_stream(client: &Client, url: &str) -> Result<StreamProbe> {
+        let response = client
+            .get(url)
+            .header("Icy-MetaData", "1")
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| SourceError::Other(e.without_url().to_string()))?;
+        match response.status() {
+            StatusCode::FORBIDDEN => return Err(SourceError::Forbidden.into()),
+            StatusCode::TOO_MANY_REQUESTS => return Err(SourceError::RateLimited.into()),
+            status if !status.is_success() => return Err(SourceError::Unavailable.into()),
+            _ => {}
+        }
+
+        let headers = response.headers();
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let content_type = header("content-type")
+            .and_then(|v| v.split(';').next().map(|t| t.trim().to_lowercase()))
+            .unwrap_or_default();
+        if PLAYLIST_TYPES.contains(&content_type.as_str()) {
+            return Err(SourceError::Other("The URL is a playlist, not audio".to_string()).into());
+        }
+
+        Ok(StreamProbe {
+            native: SONGBIRD_TYPES.contains(&content_type.as_str()),
+            station: header("icy-name"),
+            icy_metadata: header("icy-metaint").is_some(),
         })
+        // The probe response is dropped here, closing the connection before playback opens its own.
     }
+}
+
+#[async_trait::async_trait]
+impl SourceProvider for DirectHttpProvider {
+    fn name(&self) -> &'static str {
+        "direct HTTP"
+    }
+
+    fn priority(&self) -> i32 {
+        50
+    }
+
+    async fn matches(&self, url: &str, _ctx: &ProviderContext<'_>) -> bool {
+        is_direct_audio(url)
+    }
+
+    async fn fetch_metadata(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<CachedMetadata> {
+        let probe = Self::probe_stream(ctx.http_client, url).await?;
+        Ok(titled(
+            probe.station.unwrap_or_else(|| direct_title(url)),
+            None,
+        ))
+    }
+
+    /// Plays through Songbird's HTTP input, or through ffmpeg for formats Songbird
+    /// can't decode (e.g. MP3 and AAC radio).
+    async fn create_input(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<ResolvedSource> {
+        let probe = Self::probe_stream(ctx.http_client, url).await?;
+        let metadata = titled(probe.station.unwrap_or_else(|| direct_title(url)), None);
+        let diagnostics = StderrTail::default();
+
+        let mut resolved = if probe.native {
+            let source = HttpRequest::new(ctx.http_client.clone(), url.to_string());
+            ResolvedSource::new(url, Input::from(source), diagnostics, metadata)
+        } else {
+            let mut ffmpeg = ffmpeg_playback(ctx.tools, url);
+            let child = spawn_piped(&mut ffmpeg, "ffmpeg", &diagnostics)?;
+            ResolvedSource::from_pipeline(url, vec![child], diagnostics, metadata)
+        };
+        resolved.icy_metadata = probe.icy_metadata;
+        Ok(resolved)
+    }
+}
+
+/// Resolves any page yt-dlp supports, streaming through ffmpeg.
+pub struct YtDlpProvider;
 
-    async fn fetch_metadata(&self, url: &str) -> Result<YtDlpMetadata> {
-        let mut cmd = tokio::process::Command::new("yt-dlp");
+impl YtDlpProvider {
+    /// Runs `yt-dlp --dump-json` for a single track.
+    async fn dump_json(url: &str, tools: &ToolSettings) -> Result<YtDlpMetadata> {
+        let mut cmd = tokio::process::Command::new(yt_dlp_program(tools));
         cmd.args([
             "--dump-json",   // JSON Output
             "--no-playlist", // Single track only
-            "-f",
-            "bestaudio/best", // Select best audio format to get correct URL
             "-q",
-            url,
         ]);
+        // Format selection decides which stream URL is returned
+        cmd.args(yt_dlp_user_args(tools));
+        cmd.arg(url);
 
         #[cfg(target_os = "windows")]
         cmd.creation_flags(CREATE_NO_WINDOW);
@@ -148,10 +1010,11 @@ impl SourceResolver {
         let output = cmd
             .output()
             .await
-            .context("Failed to execute yt-dlp for metadata")?;
+            .map_err(|e| SourceError::from_spawn("yt-dlp", e))?;
 
         if !output.status.success() {
-            return Err(anyhow!("yt-dlp failed to fetch metadata"));
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SourceError::from_stderr(&stderr).into());
         }
 
         let json_str = String::from_utf8(output.stdout).context("Invalid UTF-8 in metadata")?;
@@ -160,4 +1023,551 @@ impl SourceResolver {
 
         Ok(metadata)
     }
+
+    /// Lists a playlist or search without resolving each entry.
+    async fn flat_playlist(
+        target: &str,
+        extra_args: &[&str],
+        tools: &ToolSettings,
+    ) -> Result<Vec<SearchResult>> {
+        let mut cmd = tokio::process::Command::new(yt_dlp_program(tools));
+        cmd.args(["--flat-playlist", "--dump-json", "-q"]);
+        cmd.args(extra_args);
+        cmd.args(yt_dlp_user_args(tools));
+        cmd.arg(target);
+
+        #[cfg(target_os = "windows")]
+        cmd.creation_flags(CREATE_NO_WINDOW);
+
+        let output = cmd
+            .output()
+            .await
+            .map_err(|e| SourceError::from_spawn("yt-dlp", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SourceError::from_stderr(&stderr).into());
+        }
+
+        // One JSON object per line, one line per entry
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| serde_json::from_str::<YtDlpMetadata>(line).ok())
+            .filter_map(|mut hit| {
+                let url = hit.url.take()?;
+                Some(SearchResult {
+                    url,
+                    metadata: hit.into_cached(),
+                })
+            })
+            .collect())
+    }
+
+    /// Cached metadata, or freshly fetched metadata along with the stream URL it revealed.
+    async fn metadata_and_stream(
+        url: &str,
+        tools: &ToolSettings,
+    ) -> Result<(CachedMetadata, Option<String>)> {
+        let cache = MetadataCache::shared();
+        if let Some(cached) = cache.get(url) {
+            return Ok((cached, None));
+        }
+        let fetched = Self::dump_json(url, tools).await?;
+        let stream_url = fetched.url.clone();
+        let metadata = fetched.into_cached();
+        cache.insert(url, metadata.clone());
+        Ok((metadata, stream_url))
+    }
+}
+
+#[async_trait::async_trait]
+impl SourceProvider for YtDlpProvider {
+    fn name(&self) -> &'static str {
+        "yt-dlp"
+    }
+
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    async fn matches(&self, url: &str, _ctx: &ProviderContext<'_>) -> bool {
+        url.starts_with("http://") || url.starts_with("https://")
+    }
+
+    async fn fetch_metadata(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<CachedMetadata> {
+        Ok(Self::metadata_and_stream(url, ctx.tools).await?.0)
+    }
+
+    async fn create_input(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<ResolvedSource> {
+        let tools = ctx.tools;
+        let (metadata, stream_url) = Self::metadata_and_stream(url, tools).await?;
+        let diagnostics = StderrTail::default();
+
+        // Create Audio Stream via FFmpeg
+        let children = match &stream_url {
+            Some(stream_url) => {
+                let mut ffmpeg = ffmpeg_playback(tools, stream_url);
+                vec![spawn_piped(&mut ffmpeg, "ffmpeg", &diagnostics)?]
+            }
+            // No stream URL on a metadata cache hit: yt-dlp resolves it fresh while downloading,
+            // and ffmpeg converts its output to the same format as the direct path.
+            None => spawn_download_pipeline(
+                tools,
+                url,
+                |input| ffmpeg_playback(tools, input),
+                &diagnostics,
+            )?,
+        };
+
+        // Live streams have no duration and would never finish transcoding
+        let cache_copy = if ctx.audio_cache.enabled
+            && metadata
+                .duration_secs
+                .is_some_and(|secs| secs <= MAX_CACHED_TRACK_SECS)
+        {
+            start_cache_transcode(tools, ctx.audio_cache, url, metadata.clone())
+        } else {
+            None
+        };
+
+        Ok(ResolvedSource::from_caching_pipeline(
+            url,
+            children,
+            cache_copy,
+            diagnostics,
+            metadata,
+        ))
+    }
+
+    /// Searches YouTube (`ytsearchN:`), listing hits without resolving each one.
+    async fn search(
+        &self,
+        query: &str,
+        limit: usize,
+        ctx: &ProviderContext<'_>,
+    ) -> Result<Vec<SearchResult>> {
+        Self::flat_playlist(&format!("ytsearch{}:{}", limit, query), &[], ctx.tools).await
+    }
+
+    /// Lists the video's YouTube mix, which starts with the video itself.
+    async fn related(&self, url: &str, ctx: &ProviderContext<'_>) -> Result<Vec<SearchResult>> {
+        let Some(id) = youtube_video_id(url) else {
+            return Ok(Vec::new());
+        };
+        let mix = format!("https://www.youtube.com/watch?v={}&list=RD{}", id, id);
+        Self::flat_playlist(&mix, &["--playlist-end", "25"], ctx.tools).await
+    }
+}
+
+/// Whether the URL is an HTTP(S) link to an audio file, judged by its extension alone.
+fn is_direct_audio(url: &str) -> bool {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") || is_page_host(url) {
+        return false;
+    }
+    let name = parsed.path().rsplit('/').next().unwrap_or_default();
+    name.rsplit_once('.').is_some_and(|(_, ext)| {
+        AUDIO_EXTENSIONS
+            .iter()
+            .any(|audio| ext.eq_ignore_ascii_case(audio))
+    })
+}
+
+/// Whether the URL is on a site in `PAGE_HOSTS` or one of its subdomains.
+fn is_page_host(url: &str) -> bool {
+    let Some(host) = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_lowercase))
+    else {
+        return false;
+    };
+    PAGE_HOSTS.iter().any(|page| {
+        host == *page
+            || host
+                .strip_suffix(page)
+                .is_some_and(|sub| sub.ends_with('.'))
+    })
+}
+
+/// The video ID of a YouTube watch, short or `youtu.be` link.
+fn youtube_video_id(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.trim_start_matches("www.");
+    let id = match host {
+        "youtu.be" => parsed.path_segments()?.next()?.to_string(),
+        "youtube.com" | "m.youtube.com" | "music.youtube.com" => {
+            match parsed.path().strip_prefix("/shorts/") {
+                Some(id) => id.trim_end_matches('/').to_string(),
+                None => parsed
+                    .query_pairs()
+                    .find(|(key, _)| key == "v")
+                    .map(|(_, v)| v.into_owned())?,
+            }
+        }
+        _ => return None,
+    };
+    (!id.is_empty()).then_some(id)
+}
+
+/// The provider registry, asked in descending priority order.
+pub struct SourceResolver {
+    http_client: Client,
+    providers: Vec<Box<dyn SourceProvider>>,
+}
+
+impl SourceResolver {
+    /// A resolver with the built-in providers registered.
+    ///
+    /// Bundled tools are injected into `PATH` first, since the providers run them.
+    pub fn new() -> Self {
+        inject_local_binaries();
+        Self::with_providers(vec![
+            Box::new(AudioCacheProvider),
+            Box::new(LocalFileProvider),
+            Box::new(DirectHttpProvider),
+            Box::new(YtDlpProvider),
+        ])
+    }
+
+    /// A resolver asking only the given providers, leaving `PATH` untouched.
+    pub fn with_providers(providers: Vec<Box<dyn SourceProvider>>) -> Self {
+        let mut resolver = Self {
+            http_client: Client::new(),
+            providers: Vec::new(),
+        };
+        for provider in providers {
+            resolver.register(provider);
+        }
+        resolver
+    }
+
+    /// Adds a provider, keeping the registry in priority order.
+    ///
+    /// Among equal priorities, earlier registrations are asked first.
+    pub fn register(&mut self, provider: Box<dyn SourceProvider>) {
+        let index = self
+            .providers
+            .partition_point(|p| p.priority() >= provider.priority());
+        self.providers.insert(index, provider);
+    }
+
+    /// Plays a URL or local path with the first provider that accepts it.
+    ///
+    /// Free text no provider accepts is treated as search terms, and the top hit is played.
+    /// Unmatched paths and URLs fail instead, so a typo never plays a search result.
+    pub async fn resolve(
+        &self,
+        url: &str,
+        tools: &ToolSettings,
+        audio_cache: &AudioCacheSettings,
+    ) -> Result<ResolvedSource> {
+        let ctx = ProviderContext {
+            tools,
+            audio_cache,
+            http_client: &self.http_client,
+        };
+
+        if let Some(provider) = self.find_provider(url, &ctx).await {
+            return provider.create_input(url, &ctx).await;
+        }
+        if url.contains("://") {
+            anyhow::bail!("No source can play {}", url);
+        }
+        if looks_like_path(url) {
+            anyhow::bail!("File not found: {}", url);
+        }
+
+        let hit = self
+            .search(url, 1, &ctx)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(SourceError::Unavailable)?;
+        let provider = self
+            .find_provider(&hit.url, &ctx)
+            .await
+            .ok_or(SourceError::Unavailable)?;
+        let mut resolved = provider.create_input(&hit.url, &ctx).await?;
+        // Keep what the search listed if the provider found less
+        resolved.artist = resolved.artist.or(hit.metadata.artist);
+        resolved.duration = resolved
+            .duration
+            .or(hit.metadata.duration_secs.map(Duration::from_secs_f64));
+        resolved.thumbnail_url = resolved.thumbnail_url.or(hit.metadata.thumbnail_url);
+        Ok(resolved)
+    }
+
+    /// Display metadata for a URL from the first provider that accepts it, without starting playback.
+    pub async fn fetch_metadata(
+        &self,
+        url: &str,
+        tools: &ToolSettings,
+        audio_cache: &AudioCacheSettings,
+    ) -> Result<CachedMetadata> {
+        let ctx = ProviderContext {
+            tools,
+            audio_cache,
+            http_client: &self.http_client,
+        };
+        let provider = self
+            .find_provider(url, &ctx)
+            .await
+            .ok_or(SourceError::Unavailable)?;
+        provider.fetch_metadata(url, &ctx).await
+    }
+
+    /// Display metadata for listing a URL before it is played, if it is known without
+    /// running yt-dlp or requesting the URL.
+    ///
+    /// The metadata cache is checked first; other remote URLs are unknown until they are
+    /// resolved, which still happens fresh at play time since stream URLs expire.
+    pub async fn metadata(
+        &self,
+        url: &str,
+        tools: &ToolSettings,
+        audio_cache: &AudioCacheSettings,
+    ) -> Option<CachedMetadata> {
+        if let Some(cached) = MetadataCache::shared().get(url) {
+            return Some(cached);
+        }
+        local_file(url)?;
+        self.fetch_metadata(url, tools, audio_cache).await.ok()
+    }
+
+    /// The highest-priority provider that accepts the URL.
+    async fn find_provider(
+        &self,
+        url: &str,
+        ctx: &ProviderContext<'_>,
+    ) -> Option<&dyn SourceProvider> {
+        for provider in &self.providers {
+            if provider.matches(url, ctx).await {
+                return Some(provider.as_ref());
+            }
+        }
+        None
+    }
+
+    /// Results from the first provider that finds anything for the query.
+    async fn search(
+        &self,
+        query: &str,
+        limit: usize,
+        ctx: &ProviderContext<'_>,
+    ) -> Result<Vec<SearchResult>> {
+        let mut last_error = None;
+        for provider in &self.providers {
+            match provider.search(query, limit, ctx).await {
+                Ok(results) if !results.is_empty() => return Ok(results),
+                Ok(_) => {}
+                Err(e) => {
+                    logging::record(
+                        LogLevel::Warn,
+                        format!("{} search failed: {:#}", provider.name(), e),
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) => Err(e),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Tracks related to a URL, from the first provider that recommends any.
+    pub async fn related(
+        &self,
+        url: &str,
+        tools: &ToolSettings,
+        audio_cache: &AudioCacheSettings,
+    ) -> Result<Vec<SearchResult>> {
+        let ctx = ProviderContext {
+            tools,
+            audio_cache,
+            http_client: &self.http_client,
+        };
+        for provider in &self.providers {
+            let results = provider.related(url, &ctx).await?;
+            if !results.is_empty() {
+                return Ok(results);
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// A client for HTTP requests made alongside playback, such as reading radio metadata.
+    pub fn http_client(&self) -> Client {
+        self.http_client.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A provider that accepts every input, for checking registry order.
+    struct Named(&'static str, i32);
+
+    #[async_trait::async_trait]
+    impl SourceProvider for Named {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn priority(&self) -> i32 {
+            self.1
+        }
+
+        async fn matches(&self, _url: &str, _ctx: &ProviderContext<'_>) -> bool {
+            true
+        }
+
+        async fn fetch_metadata(
+            &self,
+            url: &str,
+            _ctx: &ProviderContext<'_>,
+        ) -> Result<CachedMetadata> {
+            Ok(titled(url.to_string(), None))
+        }
+
+        async fn create_input(
+            &self,
+            _url: &str,
+            _ctx: &ProviderContext<'_>,
+        ) -> Result<ResolvedSource> {
+            Err(SourceError::Unavailable.into())
+        }
+    }
+
+    fn empty_resolver() -> SourceResolver {
+        SourceResolver::with_providers(Vec::new())
+    }
+
+    fn names(resolver: &SourceResolver) -> Vec<&'static str> {
+        resolver.providers.iter().map(|p| p.name()).collect()
+    }
+
+    #[test]
+    fn register_keeps_priority_order() {
+        let mut resolver = empty_resolver();
+        resolver.register(Box::new(Named("low", 0)));
+        resolver.register(Box::new(Named("high", 100)));
+        resolver.register(Box::new(Named("mid", 50)));
+        resolver.register(Box::new(Named("mid-later", 50)));
+        assert_eq!(names(&resolver), ["high", "mid", "mid-later", "low"]);
+    }
+
+    #[tokio::test]
+    async fn find_provider_prefers_higher_priority() {
+        let mut resolver = empty_resolver();
+        resolver.register(Box::new(Named("catch-all", 0)));
+        resolver.register(Box::new(ToneProvider));
+        let tools = ToolSettings::default();
+        let audio_cache = AudioCacheSettings::default();
+        let ctx = ProviderContext {
+            tools: &tools,
+            audio_cache: &audio_cache,
+            http_client: &resolver.http_client,
+        };
+
+        let tone = resolver.find_provider("tone://440", &ctx).await;
+        assert_eq!(tone.map(|p| p.name()), Some("tone"));
+        let other = resolver.find_provider("https://example.com/a", &ctx).await;
+        assert_eq!(other.map(|p| p.name()), Some("catch-all"));
+    }
+
+    #[test]
+    fn tone_parse_reads_frequency_and_length() {
+        assert_eq!(
+            ToneProvider::parse("tone://880?secs=2").unwrap(),
+            (880.0, 2.0)
+        );
+        assert_eq!(ToneProvider::parse("tone://").unwrap(), (440.0, 5.0));
+        // Out-of-range values fall back to the defaults or the maximum
+        assert_eq!(
+            ToneProvider::parse("tone://5?secs=-1").unwrap(),
+            (440.0, 5.0)
+        );
+        assert_eq!(
+            ToneProvider::parse("tone://440?secs=99999").unwrap(),
+            (440.0, ToneProvider::MAX_SECS)
+        );
+    }
+
+    #[test]
+    fn tone_wav_has_valid_header_and_length() {
+        let wav = ToneProvider::render_wav(440.0, 0.5);
+        let frames = ToneProvider::SAMPLE_RATE / 2;
+        let data_len = frames * 4;
+        let u32_at = |i: usize| u32::from_le_bytes(wav[i..i + 4].try_into().unwrap());
+
+        assert_eq!(wav.len(), 44 + data_len as usize);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32_at(4), wav.len() as u32 - 8);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(24), ToneProvider::SAMPLE_RATE);
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(u32_at(40), data_len);
+    }
+
+    #[test]
+    fn split_args_honours_quotes() {
+        assert_eq!(
+            split_args(r#"--extractor-args "youtube:player_client=web,tv"  -N 4"#),
+            [
+                "--extractor-args",
+                "youtube:player_client=web,tv",
+                "-N",
+                "4"
+            ]
+        );
+        assert_eq!(
+            split_args(r#"--download-archive 'C:\My Music\archive.txt'"#),
+            ["--download-archive", r"C:\My Music\archive.txt"]
+        );
+        assert_eq!(split_args(r#"--referer "" -v"#), ["--referer", "", "-v"]);
+        assert_eq!(split_args("--output \"a b"), ["--output", "a b"]);
+        assert!(split_args("   ").is_empty());
+    }
+
+    #[test]
+    fn page_hosts_are_recognised() {
+        assert!(is_page_host("https://www.youtube.com/watch?v=abc"));
+        assert!(is_page_host("https://youtu.be/abc"));
+        assert!(is_page_host("https://artist.bandcamp.com/track/song"));
+        assert!(is_page_host("https://SoundCloud.com/artist/song"));
+        assert!(!is_page_host("https://notyoutube.com/stream.mp3"));
+        assert!(!is_page_host("http://radio.example.com:8000/live"));
+        assert!(!is_page_host("not a url"));
+    }
+
+    #[test]
+    fn direct_audio_is_judged_by_extension() {
+        assert!(is_direct_audio("https://example.com/music/song.mp3"));
+        assert!(is_direct_audio(
+            "http://radio.example.com:8000/live.OGG?t=1"
+        ));
+        assert!(!is_direct_audio("http://radio.example.com:8000/live"));
+        assert!(!is_direct_audio("https://example.com/list.m3u"));
+        assert!(!is_direct_audio("https://www.youtube.com/watch.mp3"));
+        assert!(!is_direct_audio("ftp://example.com/song.mp3"));
+    }
+
+    #[test]
+    fn path_like_input_is_not_searched() {
+        for path in [
+            "/music/song.mp3",
+            "./song.mp3",
+            "C:\\Users\\John Doe\\song.mp3",
+            "music/song.mp3",
+            "file:///music/song.mp3",
+        ] {
+            assert!(looks_like_path(path), "{}", path);
+        }
+        for query in ["never gonna give you up", "AC/DC Thunderstruck", "song.mp3"] {
+            assert!(!looks_like_path(query), "{}", query);
+        }
+    }
 }
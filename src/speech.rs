@@ -0,0 +1,271 @@
+//! Text-to-Speech Module
+//!
+//! Synthesizes short spoken messages with a locally installed engine
+//! (espeak-ng or piper) run as a subprocess. The text is written to the
+//! engine's stdin, so it is never parsed as command-line options, and the
+//! result is a WAV file held in memory for Songbird to play.
+//!
+//! Each guild's messages go through a `SpeechQueue`, which synthesizes and
+//! plays them one at a time off the bot's command loop, pausing the current
+//! track until the last queued message has been spoken.
+
+use crate::logging::{LogEntry, LogLevel};
+use crate::sources::background_command;
+use crate::state::{BotCommand, SharedState, SpeechEngine, SpeechSettings};
+use anyhow::{Context, Result, bail};
+use serenity::all::GuildId;
+use songbird::input::Input;
+use songbird::tracks::{PlayMode, TrackHandle};
+use songbird::{Event, EventContext, EventHandler, Songbird, TrackEvent};
+use std::fs;
+use std::io::Write;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+
+/// Longer messages are cut off, so a pasted wall of text can't hold up playback.
+pub const MAX_SAY_CHARS: usize = 300;
+
+/// Renders `text` as speech, returning a WAV file. Blocking: waits for the engine to finish.
+pub fn synthesize(settings: &SpeechSettings, text: &str) -> Result<Vec<u8>> {
+    let text: String = text.trim().chars().take(MAX_SAY_CHARS).collect();
+    if text.is_empty() {
+        bail!("Nothing to say");
+    }
+
+    let binary = settings.engine.binary_name();
+    let program = settings.binary_path.as_deref().unwrap_or(binary);
+    let mut cmd = background_command(program);
+    let voice = settings.voice.trim();
+
+    // piper can't write WAV to stdout, so it renders into a temporary file
+    let output_file = match settings.engine {
+        SpeechEngine::EspeakNg => {
+            // An empty voice leaves espeak-ng on its default
+            if !voice.is_empty() {
+                cmd.args(["-v", voice]);
+            }
+            cmd.arg("--stdout");
+            None
+        }
+        SpeechEngine::Piper => {
+            if !voice.to_lowercase().ends_with(".onnx") {
+                bail!("piper needs the path of a .onnx voice model, set under Voice");
+            }
+            let path = std::env::temp_dir().join(format!("say-{}.wav", uuid::Uuid::new_v4()));
+            cmd.args(["--model", voice, "--output_file"]);
+            cmd.arg(&path);
+            Some(path)
+        }
+    };
+
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", binary))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+        // Dropping stdin closes it, telling the engine the text is complete
+    }
+    let output = child.wait_with_output()?;
+
+    let wav = match &output_file {
+        Some(path) => {
+            let wav = fs::read(path);
+            let _ = fs::remove_file(path);
+            if output.status.success() {
+                wav.with_context(|| format!("{} produced no audio", binary))?
+            } else {
+                Vec::new()
+            }
+        }
+        None => output.stdout,
+    };
+    if !output.status.success() || wav.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().last().unwrap_or("no audio produced");
+        bail!("{} failed: {}", binary, reason.trim());
+    }
+    Ok(wav)
+}
+
+/// A guild's spoken messages, played one after another by a background task.
+pub struct SpeechQueue {
+    tx: mpsc::UnboundedSender<String>,
+    /// Set while a track is paused for speech. Cleared when the user pauses, resumes,
+    /// skips or stops, so speech never resumes a track it didn't pause.
+    resume: Arc<AtomicBool>,
+}
+
+impl SpeechQueue {
+    /// Starts the guild's speaker task. It runs until the queue is dropped.
+    pub fn start(uuid: String, guild_id: u64, state: SharedState, songbird: Arc<Songbird>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let resume = Arc::new(AtomicBool::new(false));
+        let speaker = Speaker {
+            uuid,
+            guild_id,
+            state,
+            songbird,
+            resume: resume.clone(),
+        };
+        tokio::spawn(speaker.run(rx));
+        Self { tx, resume }
+    }
+
+    /// Queues a message to be spoken after any before it.
+    pub fn say(&self, text: String) {
+        let _ = self.tx.send(text);
+    }
+
+    /// Leaves the track paused for speech to the user, who has taken over playback.
+    pub fn cancel_resume(&self) {
+        self.resume.store(false, Ordering::SeqCst);
+    }
+}
+
+/// The task behind a `SpeechQueue`.
+struct Speaker {
+    uuid: String,
+    guild_id: u64,
+    state: SharedState,
+    songbird: Arc<Songbird>,
+    resume: Arc<AtomicBool>,
+}
+
+impl Speaker {
+    async fn run(self, mut rx: mpsc::UnboundedReceiver<String>) {
+        // The track paused for speech, resumed once the queue is empty
+        let mut paused: Option<TrackHandle> = None;
+        while let Some(text) = rx.recv().await {
+            if !self.resume.load(Ordering::SeqCst) {
+                paused = None;
+            }
+            self.speak(text, &mut paused).await;
+
+            if rx.is_empty()
+                && let Some(track) = paused.take()
+                && self.resume.swap(false, Ordering::SeqCst)
+            {
+                let _ = track.play();
+            }
+        }
+    }
+
+    /// Synthesizes one message and waits until it has been played.
+    async fn speak(&self, text: String, paused: &mut Option<TrackHandle>) {
+        let settings = self
+            .state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .speech_settings
+            .clone();
+        let wav = match tokio::task::spawn_blocking(move || synthesize(&settings, &text)).await {
+            Ok(Ok(wav)) => wav,
+            Ok(Err(e)) => return self.log(format!("Speech failed: {:#}", e)),
+            Err(e) => return self.log(format!("Speech task failed: {}", e)),
+        };
+        let Some(call) = self.songbird.get(GuildId::new(self.guild_id)) else {
+            return self.log("Not connected to a voice channel.".to_string());
+        };
+
+        let (done_tx, done_rx) = oneshot::channel();
+        {
+            let mut handler = call.lock().await;
+            if paused.is_none()
+                && let Some(track) = handler.queue().current()
+                && track
+                    .get_info()
+                    .await
+                    .is_ok_and(|info| info.playing == PlayMode::Play)
+            {
+                let _ = track.pause();
+                self.resume.store(true, Ordering::SeqCst);
+                *paused = Some(track);
+            }
+
+            let clip = handler.play_input(Input::from(wav));
+            // An errored clip never ends, so either event counts
+            let done = Arc::new(Mutex::new(Some(done_tx)));
+            for event in [TrackEvent::End, TrackEvent::Error] {
+                let _ = clip.add_event(Event::Track(event), ClipDone(done.clone()));
+            }
+        }
+        // Also returns if the clip is dropped without either event, e.g. on leaving
+        let _ = done_rx.await;
+    }
+
+    fn log(&self, message: String) {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .log_entry(LogEntry::new(
+                LogLevel::Error,
+                Some(self.uuid.clone()),
+                Some(self.guild_id),
+                message,
+            ));
+    }
+}
+
+/// Tells the speaker task a clip has finished.
+struct ClipDone(Arc<Mutex<Option<oneshot::Sender<()>>>>);
+
+#[async_trait::async_trait]
+impl EventHandler for ClipDone {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if let Some(done) = self.0.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let _ = done.send(());
+        }
+        Some(Event::Cancel)
+    }
+}
+
+/// Asks the bot to say "Next up: <title>" when a track plays to its end and another
+/// follows it, if the guild has spoken announcements enabled. Skipped and stopped tracks
+/// aren't followed by an announcement.
+pub struct NextTrackSpeaker {
+    pub uuid: String,
+    pub guild_id: u64,
+    pub state: SharedState,
+    /// `TrackMetadata::uuid` of the track this handler is attached to.
+    pub track_uuid: String,
+}
+
+#[async_trait::async_trait]
+impl EventHandler for NextTrackSpeaker {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(tracks) = ctx else {
+            return None;
+        };
+        if !tracks
+            .iter()
+            .any(|(state, _)| state.playing == PlayMode::End)
+        {
+            return None;
+        }
+
+        let (tx, title) = {
+            let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let account = state.accounts.get(&self.uuid)?;
+            if !account.settings_for(self.guild_id).speak_next_track {
+                return None;
+            }
+            let guild = account.guilds.get(&self.guild_id)?;
+            // The bot may have synced the next track in as now playing already
+            let next = match &guild.now_playing {
+                Some(playing) if playing.uuid != self.track_uuid => Some(playing),
+                _ => guild.queue.front().or(guild.pending_imports.front()),
+            }?;
+            (account.command_tx.clone()?, next.title.clone())
+        };
+        let _ = tx.try_send(BotCommand::Say {
+            guild_id: self.guild_id,
+            text: format!("Next up: {}", title),
+        });
+        None
+    }
+}
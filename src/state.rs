@@ -2,9 +2,15 @@
 //!
 //! Handles global application state.
 
+use crate::diagnostics::DependencyReport;
+use crate::logging::{self, LogEntry};
+use crate::playlist::{ExportFormat, TrackList};
+use crate::scheduler::ScheduledJob;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 
 /// Commands sent from the UI to a Bot Instance.
@@ -16,7 +22,12 @@ pub enum BotCommand {
     Leave { guild_id: u64 },
 
     /// Enqueue a track from a URL.
-    Play { guild_id: u64, url: String },
+    Play {
+        guild_id: u64,
+        url: String,
+        /// Who asked for the track, shown in now-playing announcements.
+        requested_by: String,
+    },
     /// Pause playback.
     Pause { guild_id: u64 },
     /// Resume playback.
@@ -25,8 +36,16 @@ pub enum BotCommand {
     Stop { guild_id: u64 },
     /// Skip the current track.
     Skip { guild_id: u64 },
-    /// Set the volume (0.0 to 1.0).
+    /// Set the guild volume (0.0 to 1.0), which is remembered for later tracks and sessions.
     Volume { guild_id: u64, volume: f32 },
+    /// Set or clear a queued track's gain multiplier.
+    SetTrackGain {
+        guild_id: u64,
+        track_uuid: String,
+        gain: Option<f32>,
+    },
+    /// Enable or disable looping of the current track.
+    SetLoop { guild_id: u64, enabled: bool },
 
     /// Remove a specific track from the queue by its UUID.
     RemoveTrack { guild_id: u64, track_uuid: String },
@@ -38,6 +57,37 @@ pub enum BotCommand {
     },
     /// Clear all upcoming tracks from the queue.
     ClearQueue { guild_id: u64 },
+    /// Enqueue every track of an M3U/M3U8/PLS playlist from a local path or URL.
+    ImportPlaylist {
+        guild_id: u64,
+        source: String,
+        requested_by: String,
+    },
+    /// Write a guild's queue or history to a playlist file, at `path` or in the exports directory.
+    ExportTracks {
+        guild_id: u64,
+        which: TrackList,
+        format: ExportFormat,
+        path: Option<PathBuf>,
+    },
+    /// Speak a short message in voice, pausing the current track while it plays.
+    Say { guild_id: u64, text: String },
+    /// Stop playback, and optionally leave voice, after a time or at the end of the track or queue.
+    SetSleepTimer {
+        guild_id: u64,
+        after: SleepAfter,
+        leave: bool,
+    },
+    /// Cancel a pending sleep timer.
+    CancelSleepTimer { guild_id: u64 },
+    /// Queue a related or fallback track if autoplay is on and the queue has run out.
+    Autoplay { guild_id: u64 },
+    /// Play a local sound clip over the queue at its own volume.
+    PlaySound {
+        guild_id: u64,
+        path: String,
+        volume: f32,
+    },
 
     /// Refresh the list of available voice channels for a guild.
     FetchChannels { guild_id: u64 },
@@ -50,6 +100,33 @@ pub struct NameId {
     pub name: String,
 }
 
+/// The kind of channel the bot can connect to for audio.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum VoiceChannelKind {
+    Voice,
+    Stage,
+}
+
+/// A voice or stage channel, with the metadata shown in the channel picker.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VoiceChannel {
+    pub id: u64,
+    pub name: String,
+    pub kind: VoiceChannelKind,
+    /// The parent category, if any.
+    pub category: Option<NameId>,
+    pub position: u16,
+    /// Maximum number of connected users, or `None` if unlimited.
+    pub user_limit: Option<u32>,
+    /// Number of users currently connected.
+    pub member_count: usize,
+    pub can_connect: bool,
+    pub can_speak: bool,
+    /// The user limit is reached (not counting the bot) and the bot lacks Move Members.
+    #[serde(default)]
+    pub is_full: bool,
+}
+
 /// Metadata for a single audio track.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrackMetadata {
@@ -60,7 +137,212 @@ pub struct TrackMetadata {
     pub url: String,
     pub duration_secs: Option<u64>,
     pub thumbnail_url: Option<String>,
+    /// Who queued the track: the dashboard, a scheduled job or autoplay.
     pub added_by: String,
+    /// Volume multiplier applied on top of the guild volume, or `None` for unity gain.
+    #[serde(default)]
+    pub gain: Option<f32>,
+}
+
+impl TrackMetadata {
+    /// The track's volume at the given guild volume.
+    pub fn effective_volume(&self, guild_volume: f32) -> f32 {
+        guild_volume * self.gain.unwrap_or(1.0)
+    }
+}
+
+/// Per-guild behaviour settings, persisted in the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct GuildSettings {
+    /// Leave the voice channel after this many minutes with an empty queue.
+    pub idle_timeout_mins: Option<u64>,
+    /// Leave the voice channel after this many minutes with no non-bot users present.
+    pub alone_timeout_mins: Option<u64>,
+    /// What to do with the queue when the bot is disconnected by someone else.
+    pub on_disconnect: DisconnectAction,
+    /// Rejoin the previous channel after a forced disconnect.
+    pub auto_rejoin: bool,
+    /// Text channel for now-playing announcements, or `None` to disable them.
+    pub announce_channel_id: Option<u64>,
+    /// Text channel for the interactive player message, or `None` to disable it.
+    pub player_channel_id: Option<u64>,
+    /// Speak "Next up: <title>" in voice when a track ends and another follows.
+    pub speak_next_track: bool,
+    /// Playback volume (0.0 to 1.0) applied to every track.
+    pub volume: f32,
+    /// Keep playing related tracks when the queue runs out.
+    pub autoplay: bool,
+    /// Playlist (path or URL) to pick from when no related track is found.
+    pub autoplay_fallback: Option<String>,
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            idle_timeout_mins: None,
+            alone_timeout_mins: None,
+            on_disconnect: DisconnectAction::Pause,
+            auto_rejoin: false,
+            announce_channel_id: None,
+            player_channel_id: None,
+            speak_next_track: false,
+            volume: 1.0,
+            autoplay: false,
+            autoplay_fallback: None,
+        }
+    }
+}
+
+/// The queue action taken when the bot is forcibly disconnected from voice.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum DisconnectAction {
+    /// Pause the current track so playback can continue after a rejoin.
+    Pause,
+    /// Stop playback and clear the queue.
+    Clear,
+}
+
+/// Locations of the external tools and extra options passed to yt-dlp, persisted in the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ToolSettings {
+    /// Explicit yt-dlp executable, or `None` to search `bin/` and PATH.
+    pub yt_dlp_path: Option<String>,
+    /// Explicit ffmpeg executable, or `None` to search `bin/` and PATH.
+    pub ffmpeg_path: Option<String>,
+    /// Netscape-format cookies file passed to yt-dlp via `--cookies`.
+    pub cookies_file: Option<String>,
+    /// Proxy URL used by yt-dlp and, for HTTP proxies, by ffmpeg.
+    pub proxy: Option<String>,
+    /// yt-dlp format selector.
+    pub format: String,
+    /// Additional yt-dlp arguments, split like a command line; quote values containing spaces.
+    pub extra_args: String,
+    /// Run yt-dlp's self-update this often, or `None` to only update on demand.
+    pub auto_update_hours: Option<u64>,
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            yt_dlp_path: None,
+            ffmpeg_path: None,
+            cookies_file: None,
+            proxy: None,
+            format: "bestaudio/best".to_string(),
+            extra_args: String::new(),
+            auto_update_hours: None,
+        }
+    }
+}
+
+/// Offline audio cache settings, persisted in the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AudioCacheSettings {
+    /// Store Opus copies of played tracks and replay them from disk.
+    pub enabled: bool,
+    /// Least recently used tracks are evicted beyond this size.
+    pub max_size_mb: u64,
+}
+
+impl Default for AudioCacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size_mb: 2048,
+        }
+    }
+}
+
+/// A locally installed text-to-speech engine.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SpeechEngine {
+    EspeakNg,
+    Piper,
+}
+
+impl SpeechEngine {
+    /// The executable name looked up in `bin/` and PATH.
+    pub fn binary_name(self) -> &'static str {
+        match self {
+            SpeechEngine::EspeakNg => "espeak-ng",
+            SpeechEngine::Piper => "piper",
+        }
+    }
+}
+
+/// Text-to-speech settings, persisted in the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SpeechSettings {
+    pub engine: SpeechEngine,
+    /// Explicit engine executable, or `None` to search `bin/` and PATH.
+    pub binary_path: Option<String>,
+    /// espeak-ng voice name (e.g. `en-us`, empty for its default), or the path of a
+    /// piper `.onnx` model.
+    pub voice: String,
+}
+
+impl Default for SpeechSettings {
+    fn default() -> Self {
+        Self {
+            engine: SpeechEngine::EspeakNg,
+            binary_path: None,
+            voice: String::new(),
+        }
+    }
+}
+
+/// When a sleep timer fires.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SleepAfter {
+    /// After a fixed time.
+    Duration(Duration),
+    /// When the current track ends.
+    AfterCurrentTrack,
+    /// When the queue runs out.
+    AfterQueue,
+}
+
+/// A guild's pending sleep timer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SleepTimer {
+    pub after: SleepAfter,
+    /// When a `SleepAfter::Duration` timer fires, as a Unix timestamp in milliseconds.
+    pub fires_at_ms: Option<i64>,
+    /// Leave the voice channel as well as stopping.
+    pub leave: bool,
+}
+
+impl SleepTimer {
+    /// A short description of when the timer fires, e.g. "in 12:30".
+    pub fn describe(&self, now_ms: i64) -> String {
+        match self.after {
+            SleepAfter::Duration(_) => {
+                let secs = self
+                    .fires_at_ms
+                    .map_or(0, |at| ((at - now_ms) / 1000).max(0));
+                format!("in {}:{:02}", secs / 60, secs % 60)
+            }
+            SleepAfter::AfterCurrentTrack => "after this track".to_string(),
+            SleepAfter::AfterQueue => "when the queue ends".to_string(),
+        }
+    }
+}
+
+/// A soundboard clip, persisted in the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SoundClip {
+    pub uuid: String,
+    pub name: String,
+    /// Local audio file, in any format ffmpeg reads.
+    pub path: String,
+    /// Playback volume (0.0 to 1.0), independent of the music volume.
+    pub volume: f32,
+    /// Name of the key that plays the clip (see `egui::Key::name`), if any.
+    pub hotkey: Option<String>,
 }
 
 /// The runtime status of a bot instance.
@@ -81,13 +363,26 @@ pub struct GuildState {
 
     pub is_playing: bool,
     pub is_paused: bool,
+    pub is_looping: bool,
     pub volume: f32,
     pub position_secs: u64,
 
     pub now_playing: Option<TrackMetadata>,
     pub queue: VecDeque<TrackMetadata>,
+    /// Tracks that started playing, oldest first, capped at [`MAX_HISTORY`].
+    pub history: VecDeque<TrackMetadata>,
 
-    pub voice_channels: Vec<NameId>,
+    /// Voice and stage channels, sorted in Discord's display order (grouped by category).
+    pub voice_channels: Vec<VoiceChannel>,
+    /// Text channels, used to pick where announcements are posted.
+    pub text_channels: Vec<NameId>,
+    /// Why the last attempt to join a channel failed, if it did.
+    pub join_error: Option<String>,
+    /// Why the last track failed to resolve or play, if it did.
+    pub source_error: Option<String>,
+    /// Playlist entries still waiting to be resolved and enqueued, as far as they are known.
+    pub pending_imports: VecDeque<TrackMetadata>,
+    pub sleep_timer: Option<SleepTimer>,
 }
 
 impl GuildState {
@@ -99,11 +394,18 @@ impl GuildState {
             channel_id: None,
             is_playing: false,
             is_paused: false,
+            is_looping: false,
             volume: 1.0,
             position_secs: 0,
             now_playing: None,
             queue: VecDeque::new(),
+            history: VecDeque::new(),
             voice_channels: Vec::new(),
+            text_channels: Vec::new(),
+            join_error: None,
+            source_error: None,
+            pending_imports: VecDeque::new(),
+            sleep_timer: None,
         }
     }
 }
@@ -124,6 +426,9 @@ pub struct AccountState {
     pub status: BotStatus,
     pub guilds: HashMap<u64, GuildState>,
 
+    /// Saved per-guild settings, keyed by guild ID.
+    pub guild_settings: HashMap<u64, GuildSettings>,
+
     #[serde(skip)]
     pub command_tx: Option<Sender<BotCommand>>,
 }
@@ -140,9 +445,18 @@ impl AccountState {
             auto_start: true,
             status: BotStatus::Offline,
             guilds: HashMap::new(),
+            guild_settings: HashMap::new(),
             command_tx: None,
         }
     }
+
+    /// Returns the settings for a guild, falling back to defaults if none are saved.
+    pub fn settings_for(&self, guild_id: u64) -> GuildSettings {
+        self.guild_settings
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default()
+    }
 }
 
 /// Stores UI-specific context for persistence (e.g., selected tab).
@@ -157,18 +471,43 @@ pub struct UiContext {
 pub struct AppState {
     pub accounts: HashMap<String, AccountState>,
     pub ui_context: UiContext,
-    pub system_logs: Vec<String>,
+    pub system_logs: VecDeque<LogEntry>,
+    /// Result of the most recent yt-dlp/ffmpeg health check.
+    pub dependency_report: Option<DependencyReport>,
+    /// Set while a health check is in flight.
+    pub dependency_check_running: bool,
+    pub tool_settings: ToolSettings,
+    pub audio_cache_settings: AudioCacheSettings,
+    pub speech_settings: SpeechSettings,
+    pub soundboard: Vec<SoundClip>,
+    pub scheduled_jobs: Vec<ScheduledJob>,
+    /// Set while yt-dlp is updating itself.
+    pub yt_dlp_updating: bool,
+    /// Number of tracks currently being resolved across all bots, counting every call that
+    /// may run yt-dlp (searches and autoplay recommendations included).
+    pub resolving_tracks: usize,
 }
 
 pub type SharedState = Arc<Mutex<AppState>>;
 
+/// Number of played tracks remembered per guild.
+pub const MAX_HISTORY: usize = 500;
+
+/// Number of log entries kept in memory for the log panel.
+pub const MAX_LOG_ENTRIES: usize = 1000;
+
 impl AppState {
-    /// Appends a log message to the system logs with a timestamp.
-    pub fn log(&mut self, msg: &str) {
-        let timestamp = chrono::Local::now().format("%H:%M:%S");
-        self.system_logs.push(format!("[{}] {}", timestamp, msg));
-        if self.system_logs.len() > 1000 {
-            self.system_logs.remove(0);
+    /// Records a log entry in memory and queues it for the on-disk log.
+    pub fn log_entry(&mut self, entry: LogEntry) {
+        logging::append(&entry);
+        self.push_log(entry);
+    }
+
+    /// Records a log entry in memory only.
+    pub fn push_log(&mut self, entry: LogEntry) {
+        self.system_logs.push_back(entry);
+        if self.system_logs.len() > MAX_LOG_ENTRIES {
+            self.system_logs.pop_front();
         }
     }
 }